@@ -0,0 +1,19 @@
+//! Client and parsers for the National Data Buoy Center (NDBC) website.
+//!
+//! The [`ndbc::client::NdbcClient`] downloads station lists, realtime files and historic archives from NDBC
+//! and parses them into the types in [`ndbc::ndbc_schema`]. The parsers in [`ndbc::parser`] can also be used
+//! on their own for files that were downloaded some other way.
+//!
+//! ```no_run
+//! use rust_service_ndbc_noaa::ndbc::client::{NdbcClient, NdbcClientConfig};
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let client = NdbcClient::new(NdbcClientConfig::default())?;
+//! let spec = client.get_station_realtime_spec_data("46014").await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The HTTP server is built with the default `server` feature, library users can disable it with `default-features = false`.
+
+pub mod ndbc;
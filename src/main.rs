@@ -1,197 +1,309 @@
-mod ndbc;
-
-use actix_web::{get, web, App, HttpServer, Responder};
-use log::debug;
-use ndbc::{
-    historic::{get_historic_files, get_station_available_downloads, get_station_historical_cwind_data, get_station_historical_stdmet_data},
-    ndbc_schema::{Station, StationDataType, StationHistoricFile, StationRealtimeFile,StationStdMetData, StationContinuousWindsData, StationSpectralWaveSummary},
-    realtime::{get_active_stations, get_realtime_files, get_station_realtime_cwind_data, get_station_realtime_spec_data, get_station_realtime_stdmet_data, get_station_realtime_stdmetdrift_data},
+use actix_web::{delete, get, http::header::AUTHORIZATION, web, App, HttpRequest, HttpServer, Responder};
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use log::{debug, warn};
+use rust_service_ndbc_noaa::ndbc::{
+    client::{NdbcClient, NdbcClientConfig},
+    error::NdbcError,
+    ndbc_schema::{BoundingBox, Station, StationContinuousWindsData, StationDartData, StationDataType, StationDriftData, StationDriftTrack, StationHistoricFile, StationLatestObservation, StationMetadata, StationObservationType, StationOceanCurrentData, StationOceanographicData, StationRainSeries, StationRealtimeFile, StationSolarRadiationData, StationSpectralData, StationSpectralWaveSummary, StationStdMetData, StationSupplementalData, StationTableEntry, StationWaterLevelData},
 };
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+struct StationQuery {
+    include: Option<String>,
+    hull: Option<String>,
+    payload: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LatestObservationsQuery {
+    bbox: Option<String>,
+    data_type: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TimeRangeQuery {
+    start: Option<String>,
+    end: Option<String>,
+}
 
 #[get("/station")]
-async fn service_active_stations() -> Result<impl Responder, Box<dyn std::error::Error>> {
+async fn service_active_stations(client: web::Data<NdbcClient>, query: web::Query<StationQuery>) -> Result<impl Responder, NdbcError> {
     debug!("service_active_stations");
-    let active_stations: Vec<Station> = get_active_stations().await?;
+    let include_inactive: bool = query.include.as_deref() == Some("inactive");
+
+    // stationmetadata.xml is only needed for the stations that are no longer active
+    let stations_metadata = async {
+        if include_inactive {
+            client.get_stations_metadata().await
+        } else {
+            Ok(Vec::new())
+        }
+    };
+
+    // the listings are independent of each other, so they are fetched concurrently
+    let (listings, station_table) = futures::join!(async { futures::try_join!(client.get_active_stations(), stations_metadata, client.get_historic_files(StationDataType::StandardMeteorological), client.get_historic_files(StationDataType::ContinuousWinds), client.get_historic_files(StationDataType::OceanCurrent), client.get_historic_files(StationDataType::Oceanographic), client.get_historic_files(StationDataType::WaterLevel), client.get_realtime_files(StationDataType::StandardMeteorological), client.get_realtime_files(StationDataType::ContinuousWinds), client.get_realtime_files(StationDataType::SpectralWaveSummary), client.get_realtime_files(StationDataType::Oceanographic), client.get_realtime_files(StationDataType::Supplemental),) }, station_table(&client));
+    let (active_stations, stations_metadata, stdmet_history, cwind_history, adcp_history, ocean_history, wlevel_history, stdmet_realtime, cwind_realtime, spec_realtime, ocean_realtime, supl_realtime) = listings?;
 
     if active_stations.is_empty() {
         debug!("No active stations were found");
     }
 
-    let mut historic_data = get_historic_files(StationDataType::StandardMeteorological).await?;
-
-    let mut enhanced_stations: Vec<Station> = active_stations
+    let mut stations: Vec<Station> = active_stations
         .into_iter()
         .map(|mut s: Station| {
-            let tmp: Vec<StationHistoricFile> = historic_data.iter().filter(|d: &&StationHistoricFile| d.station == s.id).map(|x: &StationHistoricFile| x.clone()).collect();
-
-            if tmp.is_empty() {
-                debug!("No historic data was found for station: {}", &s.id);
-            } else {
-                s.stdmet_history = Some(tmp);
-            }
-
+            s.active = Some(true);
             s
         })
         .collect();
 
-    historic_data = get_historic_files(StationDataType::ContinuousWinds).await?;
+    let inactive_stations: Vec<Station> = stations_metadata.iter().filter(|m: &&StationMetadata| !stations.iter().any(|s: &Station| s.id == m.id)).map(Station::from_metadata).collect();
+    stations.extend(inactive_stations);
 
-    enhanced_stations = enhanced_stations
+    // station_table.txt adds the hull, payload and time zone, which the hull and payload filters below depend on
+    for s in stations.iter_mut() {
+        match station_table.iter().find(|e: &&StationTableEntry| e.id == s.id) {
+            Some(entry) => s.merge_station_table(entry),
+            None => debug!("No station table entry was found for station: {}", &s.id),
+        }
+    }
+
+    if let Some(hull) = &query.hull {
+        stations.retain(|s: &Station| s.hull.as_deref().is_some_and(|h: &str| h.eq_ignore_ascii_case(hull)));
+    }
+    if let Some(payload) = &query.payload {
+        stations.retain(|s: &Station| s.payload.as_deref().is_some_and(|p: &str| p.eq_ignore_ascii_case(payload)));
+    }
+
+    let enhanced_stations: Vec<Station> = stations
         .into_iter()
         .map(|mut s: Station| {
-            let tmp: Vec<StationHistoricFile> = historic_data.iter().filter(|d: &&StationHistoricFile| d.station == s.id).map(|x: &StationHistoricFile| x.clone()).collect();
-
-            if tmp.is_empty() {
-                debug!("No historic data was found for station: {}", &s.id);
-            } else {
-                s.cwind_history = Some(tmp);
+            s.stdmet_history = station_files(&stdmet_history, &s.id, "historic stdmet");
+            s.cwind_history = station_files(&cwind_history, &s.id, "historic cwind");
+            s.adcp_history = station_files(&adcp_history, &s.id, "historic adcp");
+            s.ocean_history = station_files(&ocean_history, &s.id, "historic ocean");
+            s.wlevel_history = station_files(&wlevel_history, &s.id, "historic wlevel");
+            s.stdmet_realtime = station_files(&stdmet_realtime, &s.id, "realtime stdmet");
+            s.cwind_realtime = station_files(&cwind_realtime, &s.id, "realtime cwind");
+            s.spec_realtime = station_files(&spec_realtime, &s.id, "realtime spec");
+            s.supl_realtime = station_files(&supl_realtime, &s.id, "realtime supl");
+
+            // NDBC only publishes ocean files for stations flagged with water quality sensors
+            if s.waterquality == Some(true) {
+                s.ocean_realtime = station_files(&ocean_realtime, &s.id, "realtime ocean");
             }
 
             s
         })
         .collect();
 
-    let stdmet_realtime: Vec<StationRealtimeFile> = get_realtime_files(StationDataType::StandardMeteorological).await?;
-
-    enhanced_stations = enhanced_stations
-        .into_iter()
-        .map(|mut s: Station| {
-            let tmp: Vec<StationRealtimeFile> = stdmet_realtime.iter().filter(|d: &&StationRealtimeFile| d.station == s.id).map(|x: &StationRealtimeFile| x.clone()).collect();
+    Ok(web::Json(enhanced_stations))
+}
 
-            if tmp.is_empty() {
-                debug!("No realtime data was found for station: {}", &s.id);
-            } else {
-                s.stdmet_realtime = Some(tmp);
-            }
+#[get("/station/{id}")]
+async fn service_station_metadata(client: web::Data<NdbcClient>, path: web::Path<String>) -> Result<impl Responder, NdbcError> {
+    debug!("service_station_metadata");
 
-            s
-        })
-        .collect();
+    let id: String = path.into_inner();
+    let station_id: String = id.to_uppercase();
 
-    let cwind_realtime = get_realtime_files(StationDataType::ContinuousWinds).await?;
+    let (listings, station_table) = futures::join!(async { futures::try_join!(client.get_active_stations(), client.get_station_available_downloads(&id, StationDataType::StandardMeteorological), client.get_station_available_downloads(&id, StationDataType::ContinuousWinds), client.get_station_available_downloads(&id, StationDataType::OceanCurrent), client.get_station_available_downloads(&id, StationDataType::Oceanographic), client.get_station_available_downloads(&id, StationDataType::WaterLevel), client.get_realtime_files(StationDataType::StandardMeteorological), client.get_realtime_files(StationDataType::ContinuousWinds), client.get_realtime_files(StationDataType::SpectralWaveSummary), client.get_realtime_files(StationDataType::Oceanographic), client.get_realtime_files(StationDataType::Supplemental),) }, station_table(&client));
+    let (active_stations, historic_stdmet_data, historic_cwind_data, historic_adcp_data, historic_ocean_data, historic_wlevel_data, stdmet_realtime, cwind_realtime, spec_realtime, ocean_realtime, supl_realtime) = listings?;
 
-    enhanced_stations = enhanced_stations
-        .into_iter()
-        .map(|mut s: Station| {
-            let tmp: Vec<StationRealtimeFile> = cwind_realtime.iter().filter(|d: &&StationRealtimeFile| d.station == s.id).map(|x: &StationRealtimeFile| x.clone()).collect();
+    let has_history: bool = !(historic_stdmet_data.is_empty() && historic_cwind_data.is_empty() && historic_adcp_data.is_empty() && historic_ocean_data.is_empty() && historic_wlevel_data.is_empty());
 
-            if tmp.is_empty() {
-                debug!("No realtime data was found for station: {}", &s.id);
-            } else {
-                s.cwind_realtime = Some(tmp);
+    let mut station: Station = match active_stations.into_iter().find(|s: &Station| s.id == station_id) {
+        Some(mut s) => {
+            s.active = Some(true);
+            s
+        }
+        None => {
+            // retired stations are no longer in activestations.xml, but stationmetadata.xml and the historic archives still know about them
+            debug!("Station {station_id} is not active, falling back to the station metadata");
+
+            match client.get_station_metadata(&station_id).await {
+                Ok(metadata) => Station::from_metadata(&metadata),
+                Err(NdbcError::UnknownStation { .. }) if has_history => Station { id: station_id.clone(), active: Some(false), ..Default::default() },
+                Err(e) => {
+                    debug!("No metadata was found for station: {station_id}");
+                    return Err(e);
+                }
             }
+        }
+    };
 
-            s
-        })
-        .collect();
+    match station_table.iter().find(|e: &&StationTableEntry| e.id == station.id) {
+        Some(entry) => station.merge_station_table(entry),
+        None => debug!("No station table entry was found for station: {station_id}"),
+    }
 
-    let spec_realtime = get_realtime_files(StationDataType::SpectralWaveSummary).await?;
+    station.stdmet_history = station_files(&historic_stdmet_data, &station.id, "historic stdmet");
+    station.cwind_history = station_files(&historic_cwind_data, &station.id, "historic cwind");
+    station.adcp_history = station_files(&historic_adcp_data, &station.id, "historic adcp");
+    station.ocean_history = station_files(&historic_ocean_data, &station.id, "historic ocean");
+    station.wlevel_history = station_files(&historic_wlevel_data, &station.id, "historic wlevel");
+    station.stdmet_realtime = station_files(&stdmet_realtime, &station.id, "realtime stdmet");
+    station.cwind_realtime = station_files(&cwind_realtime, &station.id, "realtime cwind");
+    station.spec_realtime = station_files(&spec_realtime, &station.id, "realtime spec");
+    station.supl_realtime = station_files(&supl_realtime, &station.id, "realtime supl");
+
+    if station.waterquality == Some(true) {
+        station.ocean_realtime = station_files(&ocean_realtime, &station.id, "realtime ocean");
+    }
 
-    enhanced_stations = enhanced_stations
-        .into_iter()
-        .map(|mut s: Station| {
-            let tmp: Vec<StationRealtimeFile> = spec_realtime.iter().filter(|d: &&StationRealtimeFile| d.station == s.id).map(|x: &StationRealtimeFile| x.clone()).collect();
+    Ok(web::Json(vec![station]))
+}
 
-            if tmp.is_empty() {
-                debug!("No realtime data was found for station: {}", &s.id);
-            } else {
-                s.spec_realtime = Some(tmp);
-            }
+async fn station_table(client: &NdbcClient) -> Vec<StationTableEntry> {
+    // station_table.txt only adds details (hull, payload, time zone) to the listings, so the stations are still served without it
+    match client.get_station_table().await {
+        Ok(res) => res,
+        Err(e) => {
+            warn!("Unable to fetch station_table.txt, continuing without it: {e}");
+            Vec::new()
+        }
+    }
+}
 
-            s
-        })
-        .collect();
+trait StationFile: Clone {
+    fn station(&self) -> &str;
+}
 
-    Ok(web::Json(enhanced_stations))
+impl StationFile for StationHistoricFile {
+    fn station(&self) -> &str {
+        &self.station
+    }
 }
 
-#[get("/station/{id}")]
-async fn service_station_metadata(path: web::Path<String>) -> Result<impl Responder, Box<dyn std::error::Error>> {
-    debug!("service_station_stdmet_historic_data");
+impl StationFile for StationRealtimeFile {
+    fn station(&self) -> &str {
+        &self.station
+    }
+}
 
-    let id: String = path.into_inner();
-    let active_stations: Vec<Station> = get_active_stations().await?;
-    let mut active_stdmet_stations: Vec<Station> = active_stations.into_iter().filter(|s: &Station| s.id == id).collect();
+fn station_files<T: StationFile>(files: &[T], station: &str, data_type: &str) -> Option<Vec<T>> {
+    // This function picks a station's files out of a listing, None keeps stations without any files from serializing an empty list.
+    let res: Vec<T> = files.iter().filter(|f: &&T| f.station() == station).cloned().collect();
 
-    let historic_stdmet_data: Vec<StationHistoricFile> = get_station_available_downloads(&id, StationDataType::StandardMeteorological).await?;
+    if res.is_empty() {
+        debug!("No {data_type} data was found for station: {station}");
+        None
+    } else {
+        Some(res)
+    }
+}
 
-    let historic_cwind_data: Vec<StationHistoricFile> = get_station_available_downloads(&id, StationDataType::ContinuousWinds).await?;
+#[get("/observations/latest")]
+async fn service_latest_observations(client: web::Data<NdbcClient>, query: web::Query<LatestObservationsQuery>) -> Result<impl Responder, NdbcError> {
+    debug!("service_latest_observations");
 
-    let stdmet_realtime: Vec<StationRealtimeFile> = get_realtime_files(StationDataType::StandardMeteorological).await?;
+    // the filters are checked before anything is downloaded, so a typo is reported instead of an empty list
+    let bbox: Option<BoundingBox> = match &query.bbox {
+        Some(value) => Some(BoundingBox::parse(value).ok_or_else(|| NdbcError::InvalidQuery { parameter: "bbox".to_string(), value: value.clone() })?),
+        None => None,
+    };
+    let data_types: Option<Vec<StationObservationType>> = match &query.data_type {
+        Some(value) => Some(value.split(',').map(|t: &str| StationObservationType::from_name(t).ok_or_else(|| NdbcError::InvalidQuery { parameter: "data_type".to_string(), value: t.to_string() })).collect::<Result<Vec<StationObservationType>, NdbcError>>()?),
+        None => None,
+    };
 
-    active_stdmet_stations = active_stdmet_stations
-        .into_iter()
-        .map(|mut s: Station| {
-            let tmp: Vec<StationRealtimeFile> = stdmet_realtime.iter().filter(|d: &&StationRealtimeFile| d.station == id).map(|x: &StationRealtimeFile| x.clone()).collect();
+    let mut res: Vec<StationLatestObservation> = client.get_latest_observations().await?;
 
-            if tmp.is_empty() {
-                debug!("No realtime stdmet data was found for station: {}", &s.id);
-            } else {
-                s.stdmet_realtime = Some(tmp);
-            }
+    if let Some(bbox) = bbox {
+        res.retain(|o: &StationLatestObservation| matches!((o.lat, o.lon), (Some(lat), Some(lon)) if bbox.contains(lat, lon)));
+    }
+    if let Some(data_types) = data_types {
+        // an observation is kept when it reports any of the requested types
+        res.retain(|o: &StationLatestObservation| data_types.iter().any(|t: &StationObservationType| o.observation.reports(*t)));
+    }
 
-            s
-        })
-        .collect();
+    if res.is_empty() {
+        debug!("No latest observations were found");
+    }
 
-    let cwind_realtime = get_realtime_files(StationDataType::ContinuousWinds).await?;
+    Ok(web::Json(res))
+}
 
-    active_stdmet_stations = active_stdmet_stations
-        .into_iter()
-        .map(|mut s: Station| {
-            let tmp: Vec<StationRealtimeFile> = cwind_realtime.iter().filter(|d: &&StationRealtimeFile| d.station == s.id).map(|x: &StationRealtimeFile| x.clone()).collect();
+#[get("/station/{id}/history")]
+async fn service_station_history(client: web::Data<NdbcClient>, path: web::Path<String>) -> Result<impl Responder, NdbcError> {
+    debug!("service_station_history");
+    let id: String = path.into_inner();
+    let res: StationMetadata = client.get_station_metadata(&id).await?;
 
-            if tmp.is_empty() {
-                debug!("No realtime cwind data was found for station: {}", &s.id);
-            } else {
-                s.cwind_realtime = Some(tmp);
-            }
+    if res.history.is_empty() {
+        debug!("No deployment history was found for the station: {id}");
+    }
 
-            s
-        })
-        .collect();
+    Ok(web::Json(res))
+}
 
-    let spec_realtime = get_realtime_files(StationDataType::SpectralWaveSummary).await?;
+#[get("/station/{id}/stdmet")]
+async fn service_station_stdmet_data(client: web::Data<NdbcClient>, path: web::Path<String>, query: web::Query<TimeRangeQuery>) -> Result<impl Responder, NdbcError> {
+    debug!("service_station_stdmet_data");
+    let id: String = path.into_inner();
+    let (start, end) = time_range(&query)?;
+    let res: Vec<StationStdMetData> = client.get_station_stdmet_data(&id, start, end).await?;
 
-    active_stdmet_stations = active_stdmet_stations
-        .into_iter()
-        .map(|mut s: Station| {
-            let tmp: Vec<StationRealtimeFile> = spec_realtime.iter().filter(|d: &&StationRealtimeFile| d.station == s.id).map(|x: &StationRealtimeFile| x.clone()).collect();
+    if res.is_empty() {
+        debug!("No stdmet data was found for the station: {id} between {start} and {end}");
+    }
 
-            if tmp.is_empty() {
-                debug!("No realtime spec data was found for station: {}", &s.id);
-            } else {
-                s.spec_realtime = Some(tmp);
-            }
+    Ok(web::Json(res))
+}
 
-            s
-        })
-        .collect();
+#[get("/station/{id}/cwind")]
+async fn service_station_cwind_data(client: web::Data<NdbcClient>, path: web::Path<String>, query: web::Query<TimeRangeQuery>) -> Result<impl Responder, NdbcError> {
+    debug!("service_station_cwind_data");
+    let id: String = path.into_inner();
+    let (start, end) = time_range(&query)?;
+    let res: Vec<StationContinuousWindsData> = client.get_station_cwind_data(&id, start, end).await?;
 
-    if active_stdmet_stations.is_empty() {
-        debug!("No metadata was found for station: {id}");
+    if res.is_empty() {
+        debug!("No cwind data was found for the station: {id} between {start} and {end}");
     }
 
-    if historic_stdmet_data.is_empty() {
-        debug!("No historic stdmet data was found for station: {id}");
-    } else {
-        active_stdmet_stations[0].stdmet_history = Some(historic_stdmet_data);
+    Ok(web::Json(res))
+}
+
+fn time_range(query: &TimeRangeQuery) -> Result<(NaiveDateTime, NaiveDateTime), NdbcError> {
+    // end defaults to now and start to 45 days before end, which is what the realtime files cover
+    let end: NaiveDateTime = match &query.end {
+        Some(value) => parse_query_time("end", value, true)?,
+        None => Utc::now().naive_utc(),
+    };
+    let start: NaiveDateTime = match &query.start {
+        Some(value) => parse_query_time("start", value, false)?,
+        None => end - Duration::days(45),
+    };
+
+    if start > end {
+        return Err(NdbcError::InvalidQuery { parameter: "start".to_string(), value: query.start.clone().unwrap_or_default() });
     }
 
-    if historic_cwind_data.is_empty() {
-        debug!("No historic cwind data was found for station: {id}");
-    } else {
-        active_stdmet_stations[0].cwind_history = Some(historic_cwind_data);
+    Ok((start, end))
+}
+
+fn parse_query_time(parameter: &str, value: &str, end_of_day: bool) -> Result<NaiveDateTime, NdbcError> {
+    // accepts RFC 3339 (eg. 2024-07-01T12:00:00Z), a UTC date and time (eg. 2024-07-01T12:00) or a date, which covers the whole day
+    if let Ok(t) = DateTime::parse_from_rfc3339(value) {
+        return Ok(t.naive_utc());
+    }
+    if let Some(t) = ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"].iter().find_map(|format: &&str| NaiveDateTime::parse_from_str(value, format).ok()) {
+        return Ok(t);
     }
 
-    Ok(web::Json(active_stdmet_stations))
+    match NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        Ok(d) if end_of_day => Ok(d.and_time(NaiveTime::MIN) + Duration::days(1) - Duration::seconds(1)),
+        Ok(d) => Ok(d.and_time(NaiveTime::MIN)),
+        Err(_) => Err(NdbcError::InvalidQuery { parameter: parameter.to_string(), value: value.to_string() }),
+    }
 }
 
 #[get("/station/{id}/stdmet/{year}")]
-async fn service_station_stdmet_historic_data(path: web::Path<(String, String)>) -> Result<impl Responder, Box<dyn std::error::Error>> {
+async fn service_station_stdmet_historic_data(client: web::Data<NdbcClient>, path: web::Path<(String, String)>) -> Result<impl Responder, NdbcError> {
     debug!("service_station_stdmet_historic_data");
     let (id, year) = path.into_inner();
-    let res: Vec<StationStdMetData> = get_station_historical_stdmet_data(&id, &year).await?;
+    let res: Vec<StationStdMetData> = client.get_station_historical_stdmet_data(&id, &year).await?;
 
     if res.is_empty() {
         debug!("No stdmet data was found for the station: {id} for the year of {year}");
@@ -201,10 +313,10 @@ async fn service_station_stdmet_historic_data(path: web::Path<(String, String)>)
 }
 
 #[get("/station/{id}/cwind/{year}")]
-async fn service_station_cwind_historic_data(path: web::Path<(String, String)>) -> Result<impl Responder, Box<dyn std::error::Error>> {
+async fn service_station_cwind_historic_data(client: web::Data<NdbcClient>, path: web::Path<(String, String)>) -> Result<impl Responder, NdbcError> {
     debug!("service_station_cwind_historic_data");
     let (id, year) = path.into_inner();
-    let res: Vec<StationContinuousWindsData> = get_station_historical_cwind_data(&id, &year).await?;
+    let res: Vec<StationContinuousWindsData> = client.get_station_historical_cwind_data(&id, &year).await?;
 
     if res.is_empty() {
         debug!("No cwind data was found for the station: {id} for the year of {year}");
@@ -214,10 +326,10 @@ async fn service_station_cwind_historic_data(path: web::Path<(String, String)>)
 }
 
 #[get("/station/{id}/stdmet/realtime")]
-async fn service_station_stdmet_realtime_data(path: web::Path<String>) -> Result<impl Responder, Box<dyn std::error::Error>> {
+async fn service_station_stdmet_realtime_data(client: web::Data<NdbcClient>, path: web::Path<String>) -> Result<impl Responder, NdbcError> {
     debug!("service_station_stdmet_realtime_data");
     let id: String = path.into_inner();
-    let res: Vec<StationStdMetData> = get_station_realtime_stdmet_data(&id).await?;
+    let res: Vec<StationStdMetData> = client.get_station_realtime_stdmet_data(&id).await?;
 
     if res.is_empty() {
         debug!("No realtime stdmet data was found for the station: {id}");
@@ -227,10 +339,10 @@ async fn service_station_stdmet_realtime_data(path: web::Path<String>) -> Result
 }
 
 #[get("/station/{id}/stdmetdrift/realtime")]
-async fn service_station_stdmetdrift_realtime_data(path: web::Path<String>) -> Result<impl Responder, Box<dyn std::error::Error>> {
+async fn service_station_stdmetdrift_realtime_data(client: web::Data<NdbcClient>, path: web::Path<String>) -> Result<impl Responder, NdbcError> {
     debug!("service_station_stdmetdrift_realtime_data");
     let id: String = path.into_inner();
-    let res: Vec<StationStdMetData> = get_station_realtime_stdmetdrift_data(&id).await?;
+    let res: Vec<StationDriftData> = client.get_station_realtime_stdmetdrift_data(&id).await?;
 
     if res.is_empty() {
         debug!("No realtime stdmetdrift data was found for the station: {id}");
@@ -239,11 +351,25 @@ async fn service_station_stdmetdrift_realtime_data(path: web::Path<String>) -> R
     Ok(web::Json(res))
 }
 
+#[get("/station/{id}/stdmetdrift/track")]
+async fn service_station_stdmetdrift_track(client: web::Data<NdbcClient>, path: web::Path<String>) -> Result<impl Responder, NdbcError> {
+    debug!("service_station_stdmetdrift_track");
+    let id: String = path.into_inner();
+    let data: Vec<StationDriftData> = client.get_station_realtime_stdmetdrift_data(&id).await?;
+    let res: StationDriftTrack = StationDriftTrack::new(&id, &data);
+
+    if res.geometry.coordinates.is_empty() {
+        debug!("No drifting buoy positions were found for the station: {id}");
+    }
+
+    Ok(web::Json(res))
+}
+
 #[get("/station/{id}/cwind/realtime")]
-async fn service_station_cwind_realtime_data(path: web::Path<String>) -> Result<impl Responder, Box<dyn std::error::Error>> {
+async fn service_station_cwind_realtime_data(client: web::Data<NdbcClient>, path: web::Path<String>) -> Result<impl Responder, NdbcError> {
     debug!("service_station_cwind_realtime_data");
     let id: String = path.into_inner();
-    let res: Vec<StationContinuousWindsData> = get_station_realtime_cwind_data(&id).await?;
+    let res: Vec<StationContinuousWindsData> = client.get_station_realtime_cwind_data(&id).await?;
 
     if res.is_empty() {
         debug!("No realtime cwind data was found for the station: {id}");
@@ -253,10 +379,10 @@ async fn service_station_cwind_realtime_data(path: web::Path<String>) -> Result<
 }
 
 #[get("/station/{id}/spec/realtime")]
-async fn service_station_spec_realtime_data(path: web::Path<String>) -> Result<impl Responder, Box<dyn std::error::Error>> {
+async fn service_station_spec_realtime_data(client: web::Data<NdbcClient>, path: web::Path<String>) -> Result<impl Responder, NdbcError> {
     debug!("service_station_spec_realtime_data");
     let id: String = path.into_inner();
-    let res: Vec<StationSpectralWaveSummary> = get_station_realtime_spec_data(&id).await?;
+    let res: Vec<StationSpectralWaveSummary> = client.get_station_realtime_spec_data(&id).await?;
 
     if res.is_empty() {
         debug!("No realtime spec data was found for the station: {id}");
@@ -265,6 +391,314 @@ async fn service_station_spec_realtime_data(path: web::Path<String>) -> Result<i
     Ok(web::Json(res))
 }
 
+#[get("/station/{id}/adcp/realtime")]
+async fn service_station_adcp_realtime_data(client: web::Data<NdbcClient>, path: web::Path<String>) -> Result<impl Responder, NdbcError> {
+    debug!("service_station_adcp_realtime_data");
+    let id: String = path.into_inner();
+    let res: Vec<StationOceanCurrentData> = client.get_station_realtime_adcp_data(&id).await?;
+
+    if res.is_empty() {
+        debug!("No realtime adcp data was found for the station: {id}");
+    }
+
+    Ok(web::Json(res))
+}
+
+#[get("/station/{id}/ocean/realtime")]
+async fn service_station_ocean_realtime_data(client: web::Data<NdbcClient>, path: web::Path<String>) -> Result<impl Responder, NdbcError> {
+    debug!("service_station_ocean_realtime_data");
+    let id: String = path.into_inner();
+    let res: Vec<StationOceanographicData> = client.get_station_realtime_ocean_data(&id).await?;
+
+    if res.is_empty() {
+        debug!("No realtime ocean data was found for the station: {id}");
+    }
+
+    Ok(web::Json(res))
+}
+
+#[get("/station/{id}/supl/realtime")]
+async fn service_station_supl_realtime_data(client: web::Data<NdbcClient>, path: web::Path<String>) -> Result<impl Responder, NdbcError> {
+    debug!("service_station_supl_realtime_data");
+    let id: String = path.into_inner();
+    let res: Vec<StationSupplementalData> = client.get_station_realtime_supl_data(&id).await?;
+
+    if res.is_empty() {
+        debug!("No realtime supl data was found for the station: {id}");
+    }
+
+    Ok(web::Json(res))
+}
+
+#[get("/station/{id}/rain/realtime")]
+async fn service_station_rain_realtime_data(client: web::Data<NdbcClient>, path: web::Path<String>) -> Result<impl Responder, NdbcError> {
+    debug!("service_station_rain_realtime_data");
+    let id: String = path.into_inner();
+    let res: StationRainSeries = client.get_station_realtime_rain_data(&id).await?;
+
+    if res.data.is_empty() {
+        debug!("No realtime rain data was found for the station: {id}");
+    }
+
+    Ok(web::Json(res))
+}
+
+#[get("/station/{id}/srad/realtime")]
+async fn service_station_srad_realtime_data(client: web::Data<NdbcClient>, path: web::Path<String>) -> Result<impl Responder, NdbcError> {
+    debug!("service_station_srad_realtime_data");
+    let id: String = path.into_inner();
+    let res: Vec<StationSolarRadiationData> = client.get_station_realtime_srad_data(&id).await?;
+
+    if res.is_empty() {
+        debug!("No realtime srad data was found for the station: {id}");
+    }
+
+    Ok(web::Json(res))
+}
+
+#[get("/station/{id}/srad/{year}")]
+async fn service_station_srad_historic_data(client: web::Data<NdbcClient>, path: web::Path<(String, String)>) -> Result<impl Responder, NdbcError> {
+    debug!("service_station_srad_historic_data");
+    let (id, year) = path.into_inner();
+    let res: Vec<StationSolarRadiationData> = client.get_station_historical_srad_data(&id, &year).await?;
+
+    if res.is_empty() {
+        debug!("No srad data was found for the station: {id} for the year of {year}");
+    }
+
+    Ok(web::Json(res))
+}
+
+#[get("/station/{id}/dart/realtime")]
+async fn service_station_dart_realtime_data(client: web::Data<NdbcClient>, path: web::Path<String>) -> Result<impl Responder, NdbcError> {
+    debug!("service_station_dart_realtime_data");
+    let id: String = path.into_inner();
+    let res: Vec<StationDartData> = client.get_station_realtime_dart_data(&id).await?;
+
+    if res.is_empty() {
+        debug!("No realtime dart data was found for the station: {id}");
+    }
+
+    Ok(web::Json(res))
+}
+
+#[get("/station/{id}/dart/{year}")]
+async fn service_station_dart_historic_data(client: web::Data<NdbcClient>, path: web::Path<(String, String)>) -> Result<impl Responder, NdbcError> {
+    debug!("service_station_dart_historic_data");
+    let (id, year) = path.into_inner();
+    let res: Vec<StationDartData> = client.get_station_historical_dart_data(&id, &year).await?;
+
+    if res.is_empty() {
+        debug!("No dart data was found for the station: {id} for the year of {year}");
+    }
+
+    Ok(web::Json(res))
+}
+
+#[get("/station/{id}/swden/realtime")]
+async fn service_station_swden_realtime_data(client: web::Data<NdbcClient>, path: web::Path<String>) -> Result<impl Responder, NdbcError> {
+    debug!("service_station_swden_realtime_data");
+    let id: String = path.into_inner();
+    let res: Vec<StationSpectralData> = client.get_station_realtime_spectral_data(&id, StationDataType::SpectralWaveDensity).await?;
+
+    if res.is_empty() {
+        debug!("No realtime swden data was found for the station: {id}");
+    }
+
+    Ok(web::Json(res))
+}
+
+#[get("/station/{id}/swdir/realtime")]
+async fn service_station_swdir_realtime_data(client: web::Data<NdbcClient>, path: web::Path<String>) -> Result<impl Responder, NdbcError> {
+    debug!("service_station_swdir_realtime_data");
+    let id: String = path.into_inner();
+    let res: Vec<StationSpectralData> = client.get_station_realtime_spectral_data(&id, StationDataType::SpectralWaveA1Density).await?;
+
+    if res.is_empty() {
+        debug!("No realtime swdir data was found for the station: {id}");
+    }
+
+    Ok(web::Json(res))
+}
+
+#[get("/station/{id}/swdir2/realtime")]
+async fn service_station_swdir2_realtime_data(client: web::Data<NdbcClient>, path: web::Path<String>) -> Result<impl Responder, NdbcError> {
+    debug!("service_station_swdir2_realtime_data");
+    let id: String = path.into_inner();
+    let res: Vec<StationSpectralData> = client.get_station_realtime_spectral_data(&id, StationDataType::SpectralWaveA2Density).await?;
+
+    if res.is_empty() {
+        debug!("No realtime swdir2 data was found for the station: {id}");
+    }
+
+    Ok(web::Json(res))
+}
+
+#[get("/station/{id}/swr1/realtime")]
+async fn service_station_swr1_realtime_data(client: web::Data<NdbcClient>, path: web::Path<String>) -> Result<impl Responder, NdbcError> {
+    debug!("service_station_swr1_realtime_data");
+    let id: String = path.into_inner();
+    let res: Vec<StationSpectralData> = client.get_station_realtime_spectral_data(&id, StationDataType::SpectralWaveR1Density).await?;
+
+    if res.is_empty() {
+        debug!("No realtime swr1 data was found for the station: {id}");
+    }
+
+    Ok(web::Json(res))
+}
+
+#[get("/station/{id}/swr2/realtime")]
+async fn service_station_swr2_realtime_data(client: web::Data<NdbcClient>, path: web::Path<String>) -> Result<impl Responder, NdbcError> {
+    debug!("service_station_swr2_realtime_data");
+    let id: String = path.into_inner();
+    let res: Vec<StationSpectralData> = client.get_station_realtime_spectral_data(&id, StationDataType::SpectralWaveR2Density).await?;
+
+    if res.is_empty() {
+        debug!("No realtime swr2 data was found for the station: {id}");
+    }
+
+    Ok(web::Json(res))
+}
+
+#[get("/station/{id}/swden/{year}")]
+async fn service_station_swden_historic_data(client: web::Data<NdbcClient>, path: web::Path<(String, String)>) -> Result<impl Responder, NdbcError> {
+    debug!("service_station_swden_historic_data");
+    let (id, year) = path.into_inner();
+    let res: Vec<StationSpectralData> = client.get_station_historical_spectral_data(&id, StationDataType::SpectralWaveDensity, &year).await?;
+
+    if res.is_empty() {
+        debug!("No swden data was found for the station: {id} for the year of {year}");
+    }
+
+    Ok(web::Json(res))
+}
+
+#[get("/station/{id}/swdir/{year}")]
+async fn service_station_swdir_historic_data(client: web::Data<NdbcClient>, path: web::Path<(String, String)>) -> Result<impl Responder, NdbcError> {
+    debug!("service_station_swdir_historic_data");
+    let (id, year) = path.into_inner();
+    let res: Vec<StationSpectralData> = client.get_station_historical_spectral_data(&id, StationDataType::SpectralWaveA1Density, &year).await?;
+
+    if res.is_empty() {
+        debug!("No swdir data was found for the station: {id} for the year of {year}");
+    }
+
+    Ok(web::Json(res))
+}
+
+#[get("/station/{id}/swdir2/{year}")]
+async fn service_station_swdir2_historic_data(client: web::Data<NdbcClient>, path: web::Path<(String, String)>) -> Result<impl Responder, NdbcError> {
+    debug!("service_station_swdir2_historic_data");
+    let (id, year) = path.into_inner();
+    let res: Vec<StationSpectralData> = client.get_station_historical_spectral_data(&id, StationDataType::SpectralWaveA2Density, &year).await?;
+
+    if res.is_empty() {
+        debug!("No swdir2 data was found for the station: {id} for the year of {year}");
+    }
+
+    Ok(web::Json(res))
+}
+
+#[get("/station/{id}/swr1/{year}")]
+async fn service_station_swr1_historic_data(client: web::Data<NdbcClient>, path: web::Path<(String, String)>) -> Result<impl Responder, NdbcError> {
+    debug!("service_station_swr1_historic_data");
+    let (id, year) = path.into_inner();
+    let res: Vec<StationSpectralData> = client.get_station_historical_spectral_data(&id, StationDataType::SpectralWaveR1Density, &year).await?;
+
+    if res.is_empty() {
+        debug!("No swr1 data was found for the station: {id} for the year of {year}");
+    }
+
+    Ok(web::Json(res))
+}
+
+#[get("/station/{id}/swr2/{year}")]
+async fn service_station_swr2_historic_data(client: web::Data<NdbcClient>, path: web::Path<(String, String)>) -> Result<impl Responder, NdbcError> {
+    debug!("service_station_swr2_historic_data");
+    let (id, year) = path.into_inner();
+    let res: Vec<StationSpectralData> = client.get_station_historical_spectral_data(&id, StationDataType::SpectralWaveR2Density, &year).await?;
+
+    if res.is_empty() {
+        debug!("No swr2 data was found for the station: {id} for the year of {year}");
+    }
+
+    Ok(web::Json(res))
+}
+
+#[get("/station/{id}/adcp/{year}")]
+async fn service_station_adcp_historic_data(client: web::Data<NdbcClient>, path: web::Path<(String, String)>) -> Result<impl Responder, NdbcError> {
+    debug!("service_station_adcp_historic_data");
+    let (id, year) = path.into_inner();
+    let res: Vec<StationOceanCurrentData> = client.get_station_historical_adcp_data(&id, &year).await?;
+
+    if res.is_empty() {
+        debug!("No adcp data was found for the station: {id} for the year of {year}");
+    }
+
+    Ok(web::Json(res))
+}
+
+#[get("/station/{id}/ocean/{year}")]
+async fn service_station_ocean_historic_data(client: web::Data<NdbcClient>, path: web::Path<(String, String)>) -> Result<impl Responder, NdbcError> {
+    debug!("service_station_ocean_historic_data");
+    let (id, year) = path.into_inner();
+    let res: Vec<StationOceanographicData> = client.get_station_historical_ocean_data(&id, &year).await?;
+
+    if res.is_empty() {
+        debug!("No ocean data was found for the station: {id} for the year of {year}");
+    }
+
+    Ok(web::Json(res))
+}
+
+#[get("/station/{id}/wlevel/{year}")]
+async fn service_station_wlevel_historic_data(client: web::Data<NdbcClient>, path: web::Path<(String, String)>) -> Result<impl Responder, NdbcError> {
+    debug!("service_station_wlevel_historic_data");
+    let (id, year) = path.into_inner();
+    let res: Vec<StationWaterLevelData> = client.get_station_historical_wlevel_data(&id, &year).await?;
+
+    if res.is_empty() {
+        debug!("No wlevel data was found for the station: {id} for the year of {year}");
+    }
+
+    Ok(web::Json(res))
+}
+
+#[derive(Debug, Clone)]
+struct AdminToken(String);
+
+fn check_admin_token(req: &HttpRequest, token: &AdminToken) -> Result<(), NdbcError> {
+    // the bytes are compared without stopping at the first difference, so the response time does not tell how much of a guess was right
+    let given: &[u8] = req.headers().get(AUTHORIZATION).and_then(|v| v.to_str().ok()).and_then(|v| v.strip_prefix("Bearer ")).unwrap_or_default().as_bytes();
+    let expected: &[u8] = token.0.as_bytes();
+
+    if given.len() == expected.len() && given.iter().zip(expected).fold(0u8, |acc, (a, b)| acc | (a ^ b)) == 0 {
+        Ok(())
+    } else {
+        debug!("Rejected an admin request without a valid token");
+        Err(NdbcError::Unauthorized)
+    }
+}
+
+#[get("/admin/cache")]
+async fn service_cache_stats(client: web::Data<NdbcClient>, token: web::Data<AdminToken>, req: HttpRequest) -> Result<impl Responder, NdbcError> {
+    debug!("service_cache_stats");
+    check_admin_token(&req, &token)?;
+
+    Ok(web::Json(client.cache_stats()))
+}
+
+#[delete("/admin/cache")]
+async fn service_cache_invalidate(client: web::Data<NdbcClient>, token: web::Data<AdminToken>, req: HttpRequest) -> Result<impl Responder, NdbcError> {
+    debug!("service_cache_invalidate");
+    check_admin_token(&req, &token)?;
+    let count: usize = client.invalidate_cache();
+
+    debug!("Invalidated {count} cached NDBC documents");
+
+    Ok(web::Json(client.cache_stats()))
+}
+
 #[actix_web::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     unsafe {
@@ -274,16 +708,57 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     debug!("Starting `rust-service-ndbc-noaa` API");
 
-    HttpServer::new(|| {
+    let client: NdbcClient = NdbcClient::new(NdbcClientConfig::from_env())?;
+    debug!("Using NDBC base url {}", client.base_url());
+
+    // the admin endpoints are only served when a token is configured, the server is otherwise open to anyone who can reach it
+    let admin_token: Option<AdminToken> = std::env::var("NDBC_ADMIN_TOKEN").ok().filter(|t: &String| !t.is_empty()).map(AdminToken);
+    if admin_token.is_none() {
+        debug!("NDBC_ADMIN_TOKEN is not set, the /admin endpoints are disabled");
+    }
+
+    HttpServer::new(move || {
         App::new()
+            .app_data(web::Data::new(client.clone()))
             .service(service_active_stations)
             .service(service_station_metadata)
+            .service(service_station_history)
+            .service(service_latest_observations)
             .service(service_station_stdmet_realtime_data) // pattern match takes order from service declaration
             .service(service_station_stdmetdrift_realtime_data)
+            .service(service_station_stdmetdrift_track)
             .service(service_station_stdmet_historic_data) // overlapping patterns should be ordered with special routes first (eg. /station/ABC/realtime vs. /station/ABC/2023)
+            .service(service_station_stdmet_data)
+            .service(service_station_cwind_data)
             .service(service_station_cwind_realtime_data)
             .service(service_station_cwind_historic_data)
             .service(service_station_spec_realtime_data)
+            .service(service_station_adcp_realtime_data)
+            .service(service_station_adcp_historic_data)
+            .service(service_station_ocean_realtime_data)
+            .service(service_station_ocean_historic_data)
+            .service(service_station_supl_realtime_data)
+            .service(service_station_rain_realtime_data)
+            .service(service_station_srad_realtime_data)
+            .service(service_station_srad_historic_data)
+            .service(service_station_dart_realtime_data)
+            .service(service_station_dart_historic_data)
+            .service(service_station_wlevel_historic_data)
+            .service(service_station_swden_realtime_data)
+            .service(service_station_swden_historic_data)
+            .service(service_station_swdir_realtime_data)
+            .service(service_station_swdir_historic_data)
+            .service(service_station_swdir2_realtime_data)
+            .service(service_station_swdir2_historic_data)
+            .service(service_station_swr1_realtime_data)
+            .service(service_station_swr1_historic_data)
+            .service(service_station_swr2_realtime_data)
+            .service(service_station_swr2_historic_data)
+            .configure(|cfg| {
+                if let Some(token) = admin_token.clone() {
+                    cfg.app_data(web::Data::new(token)).service(service_cache_stats).service(service_cache_invalidate);
+                }
+            })
     })
     .bind(("0.0.0.0", 2048))?
     .run()
@@ -291,3 +766,23 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn accepts_only_the_configured_admin_token() {
+        let token: AdminToken = AdminToken("secret".to_string());
+        let request = |value: Option<&str>| match value {
+            Some(value) => TestRequest::default().insert_header((AUTHORIZATION, value)).to_http_request(),
+            None => TestRequest::default().to_http_request(),
+        };
+
+        assert!(check_admin_token(&request(Some("Bearer secret")), &token).is_ok());
+        for value in [Some("Bearer secreT"), Some("Bearer secret2"), Some("Basic secret"), Some("secret"), None] {
+            assert!(matches!(check_admin_token(&request(value), &token), Err(NdbcError::Unauthorized)), "{value:?}");
+        }
+    }
+}
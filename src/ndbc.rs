@@ -1,3 +1,15 @@
+//! Access to NDBC data: the client, the file parsers and the schema of everything returned.
+
+pub(crate) mod cache;
+pub mod client;
+pub(crate) mod coalesce;
+pub(crate) mod disk_cache;
+pub mod error;
 pub mod historic;
 pub mod ndbc_schema;
+pub mod parser;
+pub mod range;
 pub mod realtime;
+#[cfg(test)]
+mod test_server;
+pub(crate) mod throttle;
@@ -0,0 +1,183 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Size and lifetimes of the in-memory cache of index documents (station lists and directory listings).
+#[derive(Debug, Clone)]
+pub struct NdbcCacheConfig {
+    pub max_entries: usize,
+    pub realtime_ttl: Duration,
+    pub historic_ttl: Duration,
+    pub default_ttl: Duration,
+}
+
+impl Default for NdbcCacheConfig {
+    fn default() -> Self {
+        // realtime2 is refreshed every few minutes, data/historical only when NOAA compiles a new annual report
+        NdbcCacheConfig { max_entries: 256, realtime_ttl: Duration::from_secs(120), historic_ttl: Duration::from_secs(6 * 60 * 60), default_ttl: Duration::from_secs(10 * 60) }
+    }
+}
+
+/// Hit/miss counters and size of the in-memory cache.
+#[derive(Debug, Serialize, Clone)]
+pub struct NdbcCacheStats {
+    pub entries: usize,
+    pub max_entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+#[derive(Debug)]
+struct NdbcCacheEntry {
+    body: String,
+    expires: Instant,
+}
+
+#[derive(Debug)]
+pub struct NdbcCache {
+    config: NdbcCacheConfig,
+    entries: Mutex<HashMap<String, NdbcCacheEntry>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl NdbcCache {
+    pub fn new(config: NdbcCacheConfig) -> Self {
+        NdbcCache { config, entries: Mutex::new(HashMap::new()), hits: AtomicU64::new(0), misses: AtomicU64::new(0) }
+    }
+
+    pub fn ttl(&self, url: &str) -> Duration {
+        // the ttl is picked from the NDBC path of the index document
+        if url.contains("/data/realtime2/") {
+            self.config.realtime_ttl
+        } else if url.contains("/data/historical/") {
+            self.config.historic_ttl
+        } else {
+            self.config.default_ttl
+        }
+    }
+
+    pub fn get(&self, url: &str) -> Option<String> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+
+        match entries.get(url) {
+            Some(entry) if entry.expires > Instant::now() => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                Some(entry.body.clone())
+            }
+            _ => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    pub fn insert(&self, url: &str, body: &str) {
+        if self.config.max_entries == 0 {
+            return;
+        }
+
+        let now: Instant = Instant::now();
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+
+        if entries.len() >= self.config.max_entries && !entries.contains_key(url) {
+            // drop anything that has expired first, then the entry closest to expiring
+            entries.retain(|_, e| e.expires > now);
+
+            if entries.len() >= self.config.max_entries {
+                if let Some(oldest) = entries.iter().min_by_key(|(_, e)| e.expires).map(|(k, _)| k.clone()) {
+                    entries.remove(&oldest);
+                }
+            }
+        }
+
+        entries.insert(url.to_string(), NdbcCacheEntry { body: body.to_string(), expires: now + self.ttl(url) });
+    }
+
+    pub fn invalidate(&self) -> usize {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let count: usize = entries.len();
+
+        entries.clear();
+
+        count
+    }
+
+    pub fn stats(&self) -> NdbcCacheStats {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+
+        NdbcCacheStats { entries: entries.len(), max_entries: self.config.max_entries, hits: self.hits.load(Ordering::Relaxed), misses: self.misses.load(Ordering::Relaxed) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ndbc::client::NdbcClient;
+    use crate::ndbc::test_server::{TestResponse, TestServer};
+
+    fn config(max_entries: usize, ttl: Duration) -> NdbcCacheConfig {
+        NdbcCacheConfig { max_entries, realtime_ttl: ttl, historic_ttl: ttl, default_ttl: ttl }
+    }
+
+    #[test]
+    fn picks_the_ttl_from_the_path() {
+        let cache = NdbcCache::new(NdbcCacheConfig::default());
+
+        assert_eq!(cache.ttl("https://www.ndbc.noaa.gov/data/realtime2/"), Duration::from_secs(120));
+        assert_eq!(cache.ttl("https://www.ndbc.noaa.gov/data/historical/stdmet/"), Duration::from_secs(6 * 60 * 60));
+        assert_eq!(cache.ttl("https://www.ndbc.noaa.gov/activestations.xml"), Duration::from_secs(10 * 60));
+    }
+
+    #[test]
+    fn serves_entries_until_they_expire() {
+        let cache = NdbcCache::new(config(8, Duration::from_secs(60)));
+        cache.insert("a", "body");
+
+        let expired = NdbcCache::new(config(8, Duration::ZERO));
+        expired.insert("a", "body");
+
+        assert_eq!(cache.get("a").as_deref(), Some("body"));
+        assert_eq!(cache.get("b"), None);
+        assert_eq!(expired.get("a"), None);
+        assert_eq!((cache.stats().hits, cache.stats().misses), (1, 1));
+    }
+
+    #[test]
+    fn evicts_the_entry_closest_to_expiring() {
+        let cache = NdbcCache::new(NdbcCacheConfig { max_entries: 2, realtime_ttl: Duration::from_secs(10), historic_ttl: Duration::from_secs(60), default_ttl: Duration::from_secs(30) });
+        cache.insert("/data/historical/", "historic");
+        cache.insert("/data/realtime2/", "realtime");
+        cache.insert("/activestations.xml", "stations");
+
+        assert_eq!(cache.stats().entries, 2);
+        assert_eq!(cache.get("/data/realtime2/"), None);
+        assert!(cache.get("/data/historical/").is_some() && cache.get("/activestations.xml").is_some());
+        assert_eq!(cache.invalidate(), 2);
+        assert_eq!(cache.stats().entries, 0);
+    }
+
+    #[test]
+    fn does_not_cache_with_zero_entries() {
+        let cache = NdbcCache::new(config(0, Duration::from_secs(60)));
+        cache.insert("a", "body");
+
+        assert_eq!(cache.get("a"), None);
+    }
+
+    #[tokio::test]
+    async fn downloads_an_index_document_once() {
+        let server = TestServer::start();
+        server.route("/activestations.xml", TestResponse::ok(r#"<stations created="2024-10-01T00:00:00UTC" count="1"><station id="46014" lat="39.2" lon="-123.9" name="Pt Arena" /></stations>"#));
+        let client = NdbcClient::new(server.config()).unwrap();
+
+        let first = client.get_active_stations().await.unwrap();
+        let second = client.get_active_stations().await.unwrap();
+
+        assert_eq!((first.len(), second.len()), (1, 1));
+        assert_eq!(server.requests("/activestations.xml").len(), 1);
+        assert_eq!(client.cache_stats().hits, 1);
+    }
+}
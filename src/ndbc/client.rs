@@ -0,0 +1,406 @@
+pub use super::cache::{NdbcCacheConfig, NdbcCacheStats};
+pub use super::throttle::{NdbcRateLimitConfig, NdbcRetryConfig};
+
+use super::cache::NdbcCache;
+use super::coalesce::SingleFlight;
+use super::disk_cache::{DiskCache, DiskCacheEntry};
+use super::error::NdbcError;
+use super::ndbc_schema::Month;
+use super::throttle::Throttle;
+use async_compression::tokio::bufread::GzipDecoder;
+use futures::TryStreamExt;
+use log::{debug, warn};
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use std::any::TypeId;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncReadExt};
+use tokio_util::io::StreamReader;
+
+pub const DEFAULT_BASE_URL: &str = "https://www.ndbc.noaa.gov";
+pub const DEFAULT_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// Settings for an [`NdbcClient`], [`NdbcClientConfig::default`] talks to the public NDBC website.
+#[derive(Debug, Clone)]
+pub struct NdbcClientConfig {
+    pub base_url: String,
+    pub user_agent: String,
+    pub timeout: Duration,
+    pub connect_timeout: Duration,
+    pub crawl_concurrency: usize,
+    pub cache: NdbcCacheConfig,
+    pub disk_cache_dir: Option<PathBuf>,
+    pub retry: NdbcRetryConfig,
+    pub rate_limit: NdbcRateLimitConfig,
+}
+
+impl Default for NdbcClientConfig {
+    fn default() -> Self {
+        NdbcClientConfig { base_url: DEFAULT_BASE_URL.to_string(), user_agent: DEFAULT_USER_AGENT.to_string(), timeout: Duration::from_secs(30), connect_timeout: Duration::from_secs(10), crawl_concurrency: 8, cache: NdbcCacheConfig::default(), disk_cache_dir: None, retry: NdbcRetryConfig::default(), rate_limit: NdbcRateLimitConfig::default() }
+    }
+}
+
+impl NdbcClientConfig {
+    /// Reads the configuration from `NDBC_*` environment variables.
+    pub fn from_env() -> Self {
+        // This function reads the client configuration from the environment, falling back to the defaults for anything unset.
+        let mut config = NdbcClientConfig::default();
+
+        if let Ok(v) = std::env::var("NDBC_BASE_URL") {
+            config.base_url = v;
+        }
+        if let Ok(v) = std::env::var("NDBC_USER_AGENT") {
+            config.user_agent = v;
+        }
+        if let Some(v) = std::env::var("NDBC_TIMEOUT_SECS").ok().and_then(|v| v.parse().ok()) {
+            config.timeout = Duration::from_secs(v);
+        }
+        if let Some(v) = std::env::var("NDBC_CONNECT_TIMEOUT_SECS").ok().and_then(|v| v.parse().ok()) {
+            config.connect_timeout = Duration::from_secs(v);
+        }
+        if let Some(v) = std::env::var("NDBC_CRAWL_CONCURRENCY").ok().and_then(|v| v.parse().ok()) {
+            config.crawl_concurrency = v;
+        }
+        if let Some(v) = std::env::var("NDBC_CACHE_MAX_ENTRIES").ok().and_then(|v| v.parse().ok()) {
+            config.cache.max_entries = v;
+        }
+        if let Some(v) = std::env::var("NDBC_CACHE_REALTIME_TTL_SECS").ok().and_then(|v| v.parse().ok()) {
+            config.cache.realtime_ttl = Duration::from_secs(v);
+        }
+        if let Some(v) = std::env::var("NDBC_CACHE_HISTORIC_TTL_SECS").ok().and_then(|v| v.parse().ok()) {
+            config.cache.historic_ttl = Duration::from_secs(v);
+        }
+        if let Some(v) = std::env::var("NDBC_CACHE_TTL_SECS").ok().and_then(|v| v.parse().ok()) {
+            config.cache.default_ttl = Duration::from_secs(v);
+        }
+        if let Ok(v) = std::env::var("NDBC_DISK_CACHE_DIR") {
+            config.disk_cache_dir = Some(PathBuf::from(v));
+        }
+        if let Some(v) = std::env::var("NDBC_MAX_RETRIES").ok().and_then(|v| v.parse().ok()) {
+            config.retry.max_retries = v;
+        }
+        if let Some(v) = std::env::var("NDBC_RETRY_BACKOFF_MS").ok().and_then(|v| v.parse().ok()) {
+            config.retry.initial_backoff = Duration::from_millis(v);
+        }
+        if let Some(v) = std::env::var("NDBC_RETRY_MAX_BACKOFF_MS").ok().and_then(|v| v.parse().ok()) {
+            config.retry.max_backoff = Duration::from_millis(v);
+        }
+        // nan and inf parse as floats but are not a rate, they are ignored like any other invalid value
+        if let Some(v) = std::env::var("NDBC_RATE_LIMIT_PER_SEC").ok().and_then(|v| v.parse::<f64>().ok()).filter(|v: &f64| v.is_finite()) {
+            config.rate_limit.requests_per_second = v;
+        }
+        if let Some(v) = std::env::var("NDBC_RATE_LIMIT_BURST").ok().and_then(|v| v.parse().ok()) {
+            config.rate_limit.burst = v;
+        }
+        if let Some(v) = std::env::var("NDBC_MAX_CONCURRENT_REQUESTS").ok().and_then(|v| v.parse().ok()) {
+            config.rate_limit.max_concurrent = v;
+        }
+
+        config
+    }
+}
+
+/// Client for the NDBC website, cheap to clone and meant to be shared.
+#[derive(Debug, Clone)]
+pub struct NdbcClient {
+    http: reqwest::Client,
+    base_url: String,
+    crawl_concurrency: usize,
+    cache: Arc<NdbcCache>,
+    inflight: Arc<SingleFlight>,
+    disk_cache: Option<DiskCache>,
+    retry: NdbcRetryConfig,
+    throttle: Arc<Throttle>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Fetch {
+    // plain text data files (eg. data/realtime2/46014.txt)
+    Text,
+    // station lists and directory listings, these go through the cache
+    Index,
+    // gzip archives (eg. data/historical/stdmet/46014h2019.txt.gz)
+    Gzip,
+}
+
+impl NdbcClient {
+    /// Builds a client, failing only if the underlying HTTP client can not be created.
+    pub fn new(config: NdbcClientConfig) -> Result<Self, reqwest::Error> {
+        // The reqwest client holds the connection pool, so a single NdbcClient should be shared (it is cheap to clone).
+        let http = reqwest::Client::builder().user_agent(config.user_agent).timeout(config.timeout).connect_timeout(config.connect_timeout).build()?;
+
+        Ok(NdbcClient { http, base_url: config.base_url.trim_end_matches('/').to_string(), crawl_concurrency: config.crawl_concurrency.max(1), cache: Arc::new(NdbcCache::new(config.cache)), inflight: Arc::new(SingleFlight::default()), disk_cache: config.disk_cache_dir.map(DiskCache::new), retry: config.retry, throttle: Arc::new(Throttle::new(config.rate_limit)) })
+    }
+
+    /// The NDBC root every request is made against.
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Hit/miss counters and size of the in-memory cache of index documents.
+    pub fn cache_stats(&self) -> NdbcCacheStats {
+        self.cache.stats()
+    }
+
+    /// Drops every cached index document and returns how many there were.
+    pub fn invalidate_cache(&self) -> usize {
+        self.cache.invalidate()
+    }
+
+    pub(crate) fn crawl_concurrency(&self) -> usize {
+        self.crawl_concurrency
+    }
+
+    pub(crate) fn url(&self, path: &str) -> String {
+        // paths are given relative to the NDBC root (eg. "data/realtime2/")
+        self.base_url.clone() + "/" + path.trim_start_matches('/')
+    }
+
+    async fn with_retry<T, F, Fut>(&self, url: &str, request: F) -> Result<T, NdbcError>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T, NdbcError>>,
+    {
+        // This function runs an idempotent GET (including reading the body) under the rate limiter, retrying transient failures with backoff.
+        let mut attempt: u32 = 0;
+
+        loop {
+            let res: Result<T, NdbcError> = {
+                let _permit = self.throttle.acquire().await;
+                request().await
+            };
+
+            match res {
+                Err(e) if e.is_transient() && attempt < self.retry.max_retries => {
+                    let backoff: Duration = self.retry.backoff(attempt);
+                    attempt += 1;
+                    warn!("Retrying {} in {:?} (attempt {} of {}): {}", url, backoff, attempt, self.retry.max_retries, e);
+                    tokio::time::sleep(backoff).await;
+                }
+                res => return res,
+            }
+        }
+    }
+
+    async fn download_text(&self, url: &str) -> Result<String, NdbcError> {
+        // non-success statuses are surfaced as errors so an NDBC error page is never parsed as data
+        self.with_retry(url, || async move {
+            let response = self.http.get(url).send().await.and_then(|r| r.error_for_status()).map_err(|e| NdbcError::from_reqwest(url, e))?;
+
+            response.text().await.map_err(|e| NdbcError::from_reqwest(url, e))
+        })
+        .await
+    }
+
+    async fn download_disk_cached(&self, disk_cache: &DiskCache, url: &str) -> Result<Vec<u8>, NdbcError> {
+        // This function serves a file from the disk cache, revalidating it with NDBC unless it is an archive that never changes.
+        let path: &str = url.strip_prefix(self.base_url.as_str()).unwrap_or(url).trim_start_matches('/');
+        let cached: Option<DiskCacheEntry> = disk_cache.read(path).await;
+
+        if let Some(entry) = &cached {
+            if DiskCache::is_immutable(path) {
+                debug!("disk cache hit {}", url);
+                return Ok(entry.body.clone());
+            }
+        }
+
+        let downloaded: Option<DiskCacheEntry> = self
+            .with_retry(url, || async {
+                let mut request = self.http.get(url);
+                if let Some(entry) = &cached {
+                    if let Some(etag) = &entry.etag {
+                        request = request.header(IF_NONE_MATCH, etag);
+                    }
+                    if let Some(last_modified) = &entry.last_modified {
+                        request = request.header(IF_MODIFIED_SINCE, last_modified);
+                    }
+                }
+
+                let response = request.send().await.and_then(|r| r.error_for_status()).map_err(|e| NdbcError::from_reqwest(url, e))?;
+
+                if response.status() == reqwest::StatusCode::NOT_MODIFIED && cached.is_some() {
+                    return Ok(None);
+                }
+
+                let header = |name| response.headers().get(name).and_then(|v: &reqwest::header::HeaderValue| v.to_str().ok()).map(|v: &str| v.to_string());
+                let etag: Option<String> = header(ETAG);
+                let last_modified: Option<String> = header(LAST_MODIFIED);
+
+                let body: Vec<u8> = response.bytes().await.map_err(|e| NdbcError::from_reqwest(url, e))?.to_vec();
+
+                Ok(Some(DiskCacheEntry { body, etag, last_modified }))
+            })
+            .await?;
+
+        match (downloaded, cached) {
+            (Some(entry), _) => {
+                disk_cache.write(path, &entry).await;
+                Ok(entry.body)
+            }
+            (None, Some(entry)) => {
+                debug!("disk cache revalidated {}", url);
+                Ok(entry.body)
+            }
+            (None, None) => unreachable!("a 304 is only accepted when there is a cached entry"),
+        }
+    }
+
+    pub(crate) async fn get_text(&self, url: &str) -> Result<String, NdbcError> {
+        match &self.disk_cache {
+            Some(disk_cache) => Ok(String::from_utf8_lossy(&self.download_disk_cached(disk_cache, url).await?).into_owned()),
+            None => self.download_text(url).await,
+        }
+    }
+
+    pub(crate) async fn get_index_text(&self, url: &str) -> Result<String, NdbcError> {
+        // Index documents (station lists and directory listings) are small and change slowly, so they are served from the cache when possible.
+        if let Some(body) = self.cache.get(url) {
+            debug!("cache hit {}", url);
+            return Ok(body);
+        }
+
+        let body: String = self.download_text(url).await?;
+        self.cache.insert(url, &body);
+
+        Ok(body)
+    }
+
+    pub(crate) async fn get_gzip_text(&self, url: &str) -> Result<String, NdbcError> {
+        // This function downloads a gzip archive (eg. data/historical/stdmet/46014h2019.txt.gz) and decompresses it while it streams in.
+        // With the disk cache enabled the compressed archive is stored as downloaded and decompressed from there.
+        if let Some(disk_cache) = &self.disk_cache {
+            let body: Vec<u8> = self.download_disk_cached(disk_cache, url).await?;
+
+            return decode_gzip(url, body.as_slice()).await;
+        }
+
+        self.with_retry(url, || async move {
+            let response = self.http.get(url).send().await.and_then(|r| r.error_for_status()).map_err(|e| NdbcError::from_reqwest(url, e))?;
+            let stream = response.bytes_stream().map_err(std::io::Error::other);
+
+            decode_gzip(url, StreamReader::new(stream)).await
+        })
+        .await
+    }
+
+    pub(crate) async fn fetch(&self, url: &str, fetch: Fetch) -> Result<String, NdbcError> {
+        match fetch {
+            Fetch::Text => self.get_text(url).await,
+            Fetch::Index => self.get_index_text(url).await,
+            Fetch::Gzip => self.get_gzip_text(url).await,
+        }
+    }
+
+    pub(crate) async fn get_parsed<T, P>(&self, url: &str, fetch: Fetch, parse: P) -> Result<T, NdbcError>
+    where
+        T: Clone + Send + Sync + 'static,
+        P: FnOnce(&str) -> Result<T, NdbcError> + Send + 'static,
+    {
+        // This function fetches and parses an upstream document, concurrent callers for the same url share the request and the parsed result.
+        // Requests are keyed by url, result type and parser type, a parser (a closure or fn) should only depend on the body and on what it captures for that url.
+        let client: NdbcClient = self.clone();
+        let owned_url: String = url.to_string();
+
+        self.inflight
+            .run(url, TypeId::of::<P>(), async move {
+                let body: String = client.fetch(&owned_url, fetch).await?;
+
+                parse(&body)
+            })
+            .await
+    }
+}
+
+async fn decode_gzip<R: AsyncBufRead + Unpin>(url: &str, reader: R) -> Result<String, NdbcError> {
+    let mut decoder = GzipDecoder::new(reader);
+    decoder.multiple_members(true);
+
+    let mut body: String = String::new();
+    decoder.read_to_string(&mut body).await.map_err(|e| match e.into_inner().map(|i| i.downcast::<reqwest::Error>()) {
+        Some(Ok(source)) => NdbcError::from_reqwest(url, *source),
+        Some(Err(inner)) => NdbcError::MalformedDocument { url: url.to_string(), reason: inner.to_string() },
+        None => NdbcError::MalformedDocument { url: url.to_string(), reason: "invalid gzip archive".to_string() },
+    })?;
+
+    Ok(body)
+}
+
+/// Rejects anything but an NDBC station id (eg. 46014 or SAUF1), so a station never changes the url it is put in.
+pub(crate) fn check_station(station: &str) -> Result<(), NdbcError> {
+    if station.is_empty() || !station.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(NdbcError::InvalidQuery { parameter: "station".to_string(), value: station.to_string() });
+    }
+
+    Ok(())
+}
+
+/// Rejects anything but a year (eg. 2019) or a month of the current year (eg. Dec).
+pub(crate) fn check_year(year: &str) -> Result<(), NdbcError> {
+    if !(year.len() == 4 && year.chars().all(|c| c.is_ascii_digit())) && Month::from_abbreviation(year).is_none() {
+        return Err(NdbcError::InvalidQuery { parameter: "year".to_string(), value: year.to_string() });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ndbc::test_server::{gzip, TestResponse, TestServer};
+
+    #[test]
+    fn trims_the_base_url_and_joins_paths() {
+        let client = NdbcClient::new(NdbcClientConfig { base_url: "http://mirror.example/ndbc/".to_string(), ..Default::default() }).unwrap();
+
+        assert_eq!(client.base_url(), "http://mirror.example/ndbc");
+        assert_eq!(client.url("data/realtime2/"), "http://mirror.example/ndbc/data/realtime2/");
+        assert_eq!(client.url("/activestations.xml"), "http://mirror.example/ndbc/activestations.xml");
+    }
+
+    #[test]
+    fn ignores_rate_limits_that_are_not_finite() {
+        // the only test reading NDBC_RATE_LIMIT_PER_SEC, so setting it does not race with other tests
+        let rate = |value: &str| {
+            std::env::set_var("NDBC_RATE_LIMIT_PER_SEC", value);
+            NdbcClientConfig::from_env().rate_limit.requests_per_second
+        };
+
+        assert_eq!((rate("2.5"), rate("nan"), rate("inf"), rate("fast")), (2.5, 10.0, 10.0, 10.0));
+        std::env::remove_var("NDBC_RATE_LIMIT_PER_SEC");
+    }
+
+    #[tokio::test]
+    async fn fetches_from_the_configured_base_url_with_the_user_agent() {
+        let server = TestServer::start();
+        server.route("/data/realtime2/46014.txt", TestResponse::ok("#YY  MM DD hh mm WDIR WSPD\n#yr  mo dy hr mn degT m/s\n2024 10 01 00 00  270  5.0\n"));
+
+        let client = NdbcClient::new(NdbcClientConfig { user_agent: "ndbc-mirror-test".to_string(), ..server.config() }).unwrap();
+        let res = client.get_station_realtime_stdmet_data("46014").await.unwrap();
+
+        assert_eq!((res.len(), res[0].wdir), (1, Some(270.0)));
+        assert_eq!(server.requests("/data/realtime2/46014.txt")[0].header("user-agent"), Some("ndbc-mirror-test"));
+    }
+
+    #[test]
+    fn accepts_only_station_ids_and_years() {
+        assert!(check_station("46014").is_ok() && check_station("sauf1").is_ok());
+        for station in ["", "..", "46014/..", "46014.txt", "46014?x=1", "4601 4", "46%2F"] {
+            assert!(matches!(check_station(station), Err(NdbcError::InvalidQuery { parameter, .. }) if parameter == "station"), "{station}");
+        }
+
+        assert!(check_year("2019").is_ok() && check_year("Dec").is_ok());
+        for year in ["", "19", "20190", "2O19", "dec", "December", "../2019"] {
+            assert!(matches!(check_year(year), Err(NdbcError::InvalidQuery { parameter, .. }) if parameter == "year"), "{year}");
+        }
+    }
+
+    #[tokio::test]
+    async fn decodes_archives_with_several_gzip_members() {
+        let body: Vec<u8> = [gzip("#YY  MM DD hh mm\n").await, gzip("2019 01 01 00 00\n").await].concat();
+
+        assert_eq!(decode_gzip("u", body.as_slice()).await.unwrap(), "#YY  MM DD hh mm\n2019 01 01 00 00\n");
+        assert!(matches!(decode_gzip("u", b"not gzip".as_slice()).await, Err(NdbcError::MalformedDocument { .. })));
+    }
+}
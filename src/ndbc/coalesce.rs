@@ -0,0 +1,145 @@
+use super::error::NdbcError;
+use futures::future::{BoxFuture, FutureExt, Shared};
+use log::debug;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+
+type InflightFuture<T> = Shared<BoxFuture<'static, Result<T, NdbcError>>>;
+// upstream url, parsed type and parser type, two callers only share a result when they parse the same document the same way
+type InflightKey = (String, TypeId, TypeId);
+// the value is an InflightFuture<T> for the key's parsed type
+type InflightMap = HashMap<InflightKey, Box<dyn Any + Send + Sync>>;
+
+#[derive(Default)]
+pub struct SingleFlight {
+    inflight: Mutex<InflightMap>,
+}
+
+impl std::fmt::Debug for SingleFlight {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let inflight = self.inflight.lock().unwrap_or_else(|e| e.into_inner());
+
+        f.debug_struct("SingleFlight").field("inflight", &inflight.len()).finish()
+    }
+}
+
+impl SingleFlight {
+    pub async fn run<T, F>(&self, key: &str, parser: TypeId, future: F) -> Result<T, NdbcError>
+    where
+        T: Clone + Send + Sync + 'static,
+        F: Future<Output = Result<T, NdbcError>> + Send + 'static,
+    {
+        // Concurrent callers with the same key await one shared future, so the upstream request and the parsing only happen once.
+        let key: InflightKey = (key.to_string(), TypeId::of::<T>(), parser);
+
+        let shared: InflightFuture<T> = {
+            let mut inflight = self.inflight.lock().unwrap_or_else(|e| e.into_inner());
+
+            match inflight.get(&key).and_then(|f| f.downcast_ref::<InflightFuture<T>>()) {
+                Some(f) => {
+                    debug!("joining in-flight request {}", &key.0);
+                    f.clone()
+                }
+                None => {
+                    let f: InflightFuture<T> = future.boxed().shared();
+                    inflight.insert(key.clone(), Box::new(f.clone()));
+                    f
+                }
+            }
+        };
+
+        // the guard removes the entry even when the shared future fails, panics or this caller is cancelled, so a key never stays stuck
+        let guard: InflightGuard<'_, T> = InflightGuard { inflight: &self.inflight, key, shared };
+
+        guard.shared.clone().await
+    }
+}
+
+struct InflightGuard<'a, T: 'static> {
+    inflight: &'a Mutex<InflightMap>,
+    key: InflightKey,
+    shared: InflightFuture<T>,
+}
+
+impl<T: 'static> Drop for InflightGuard<'_, T> {
+    fn drop(&mut self) {
+        // the first caller to finish removes the entry, later callers start a fresh request
+        let mut inflight = self.inflight.lock().unwrap_or_else(|e| e.into_inner());
+
+        if inflight.get(&self.key).and_then(|f| f.downcast_ref::<InflightFuture<T>>()).is_some_and(|f| f.ptr_eq(&self.shared)) {
+            inflight.remove(&self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn inflight(flight: &SingleFlight) -> usize {
+        flight.inflight.lock().unwrap().len()
+    }
+
+    fn counted(calls: &Arc<AtomicUsize>, res: Result<u32, NdbcError>) -> impl Future<Output = Result<u32, NdbcError>> + Send + 'static {
+        let calls: Arc<AtomicUsize> = calls.clone();
+
+        async move {
+            calls.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            res
+        }
+    }
+
+    #[tokio::test]
+    async fn shares_one_fetch_between_concurrent_callers() {
+        let flight = SingleFlight::default();
+        let calls: Arc<AtomicUsize> = Arc::default();
+
+        let (a, b) = futures::join!(flight.run("u", TypeId::of::<()>(), counted(&calls, Ok(1))), flight.run("u", TypeId::of::<()>(), counted(&calls, Ok(2))));
+
+        assert_eq!((a.unwrap(), b.unwrap()), (1, 1));
+        assert_eq!((calls.load(Ordering::SeqCst), inflight(&flight)), (1, 0));
+    }
+
+    #[tokio::test]
+    async fn shares_errors_and_retries_afterwards() {
+        let flight = SingleFlight::default();
+        let calls: Arc<AtomicUsize> = Arc::default();
+        let error: NdbcError = NdbcError::UpstreamNotFound { url: "u".to_string() };
+
+        let (a, b) = futures::join!(flight.run("u", TypeId::of::<()>(), counted(&calls, Err(error.clone()))), flight.run("u", TypeId::of::<()>(), counted(&calls, Ok(2))));
+
+        assert!(matches!(a, Err(NdbcError::UpstreamNotFound { .. })) && matches!(b, Err(NdbcError::UpstreamNotFound { .. })));
+        assert_eq!(inflight(&flight), 0);
+        assert_eq!(flight.run("u", TypeId::of::<()>(), counted(&calls, Ok(3))).await.unwrap(), 3);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn does_not_share_between_parsers() {
+        let flight = SingleFlight::default();
+        let calls: Arc<AtomicUsize> = Arc::default();
+
+        let (a, b) = futures::join!(flight.run("u", TypeId::of::<u8>(), counted(&calls, Ok(1))), flight.run("u", TypeId::of::<u16>(), counted(&calls, Ok(2))));
+
+        assert_eq!((a.unwrap(), b.unwrap()), (1, 2));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn removes_the_entry_when_the_fetch_panics() {
+        let flight: Arc<SingleFlight> = Arc::default();
+
+        let task: Arc<SingleFlight> = flight.clone();
+        let panicked = tokio::spawn(async move { task.run::<u32, _>("u", TypeId::of::<()>(), async { panic!("unexpected listing") }).await }).await;
+
+        assert!(panicked.unwrap_err().is_panic());
+        assert_eq!(inflight(&flight), 0);
+        assert_eq!(flight.run("u", TypeId::of::<()>(), async { Ok(1u32) }).await.unwrap(), 1);
+    }
+}
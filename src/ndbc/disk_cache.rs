@@ -0,0 +1,202 @@
+use chrono::{Datelike, Utc};
+use log::{debug, warn};
+use std::path::PathBuf;
+
+// every cached file starts with this line, then one `header: value` line per validator, an empty line and the body as downloaded
+const DISK_CACHE_HEADER: &[u8] = b"ndbc-disk-cache 1\n";
+
+#[derive(Debug, Clone)]
+pub struct DiskCacheEntry {
+    pub body: Vec<u8>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DiskCache {
+    dir: PathBuf,
+}
+
+impl DiskCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        DiskCache { dir: dir.into() }
+    }
+
+    pub fn is_immutable(path: &str) -> bool {
+        // Yearly archives (eg. data/historical/stdmet/46014h2019.txt.gz) are never rewritten once NOAA publishes them.
+        // The year is the four digits in front of the extension, anything from the current year may still be replaced.
+        let Some(name) = path.strip_prefix("data/historical/").and_then(|p| p.strip_suffix(".txt.gz")) else {
+            return false;
+        };
+
+        match name.len().checked_sub(4).and_then(|i| name.get(i..)).and_then(|y| y.parse::<i32>().ok()) {
+            Some(year) => year < Utc::now().year(),
+            None => false,
+        }
+    }
+
+    fn path(&self, path: &str) -> Option<PathBuf> {
+        // the cache mirrors the NDBC directory layout, anything that could escape the cache directory is not cached
+        let path: &str = path.trim_start_matches('/');
+        if path.is_empty() || path.split('/').any(|p| p.is_empty() || p == "." || p == "..") {
+            return None;
+        }
+
+        let sanitized: String = path.chars().map(|c| if c.is_ascii_alphanumeric() || matches!(c, '/' | '.' | '-' | '_') { c } else { '_' }).collect();
+
+        Some(self.dir.join(sanitized))
+    }
+
+    pub async fn read(&self, path: &str) -> Option<DiskCacheEntry> {
+        // a file without the header (eg. one written by an older version) is treated as a miss and replaced by the next download
+        let path: PathBuf = self.path(path)?;
+        let file: Vec<u8> = tokio::fs::read(&path).await.ok()?;
+
+        let Some(mut rest) = file.strip_prefix(DISK_CACHE_HEADER) else {
+            debug!("disk cache ignoring {} without a header", path.display());
+            return None;
+        };
+
+        let mut etag: Option<String> = None;
+        let mut last_modified: Option<String> = None;
+
+        loop {
+            let end: usize = rest.iter().position(|b| *b == b'\n')?;
+            let line: String = String::from_utf8_lossy(&rest[..end]).into_owned();
+            rest = &rest[end + 1..];
+
+            match line.split_once(": ") {
+                _ if line.is_empty() => break,
+                Some(("etag", v)) => etag = Some(v.to_string()),
+                Some(("last-modified", v)) => last_modified = Some(v.to_string()),
+                _ => debug!("disk cache ignoring unknown header {:?} in {}", line, path.display()),
+            }
+        }
+
+        debug!("disk cache read {}", path.display());
+
+        Some(DiskCacheEntry { body: rest.to_vec(), etag, last_modified })
+    }
+
+    pub async fn write(&self, path: &str, entry: &DiskCacheEntry) {
+        // failing to cache is logged and otherwise ignored, the response has already been downloaded
+        let Some(path) = self.path(path) else {
+            return;
+        };
+
+        // the validators are stored in front of the body, so they are always replaced together
+        let mut file: Vec<u8> = DISK_CACHE_HEADER.to_vec();
+        if let Some(etag) = &entry.etag {
+            file.extend_from_slice(("etag: ".to_string() + etag + "\n").as_bytes());
+        }
+        if let Some(last_modified) = &entry.last_modified {
+            file.extend_from_slice(("last-modified: ".to_string() + last_modified + "\n").as_bytes());
+        }
+        file.push(b'\n');
+        file.extend_from_slice(&entry.body);
+
+        let res: std::io::Result<()> = async {
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+
+            // write to a temporary file first so a concurrent reader never sees a partial file
+            let mut tmp_path = path.clone().into_os_string();
+            tmp_path.push(".tmp");
+
+            tokio::fs::write(&tmp_path, &file).await?;
+            tokio::fs::rename(&tmp_path, &path).await
+        }
+        .await;
+
+        match res {
+            Ok(()) => debug!("disk cache write {}", path.display()),
+            Err(e) => warn!("Unable to write {} to the disk cache: {}", path.display(), e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ndbc::client::{NdbcClient, NdbcClientConfig};
+    use crate::ndbc::ndbc_schema::{StationDataType, StationHistoricFile};
+    use crate::ndbc::parser::parse_stdmet;
+    use crate::ndbc::test_server::{gzip, TestResponse, TestServer};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir: PathBuf = std::env::temp_dir().join(format!("ndbc-disk-cache-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        dir
+    }
+
+    #[test]
+    fn only_treats_previous_years_archives_as_immutable() {
+        let year: i32 = Utc::now().year();
+
+        assert!(DiskCache::is_immutable(&format!("data/historical/stdmet/46014h{}.txt.gz", year - 1)));
+        assert!(!DiskCache::is_immutable(&format!("data/historical/stdmet/46014h{year}.txt.gz")));
+        assert!(!DiskCache::is_immutable(&format!("data/stdmet/Jan/460141{year}.txt.gz")));
+        assert!(!DiskCache::is_immutable("data/realtime2/46014.txt"));
+        assert!(!DiskCache::is_immutable("data/historical/stdmet/46014h2019.txt"));
+        assert!(!DiskCache::is_immutable("data/historical/stdmet/.txt.gz"));
+    }
+
+    #[test]
+    fn keeps_paths_inside_the_cache_directory() {
+        let cache = DiskCache::new("/cache");
+
+        for path in ["", "/", "../etc/passwd", "data/../../etc/passwd", "data/./46014.txt", "data//46014.txt", "data/realtime2/"] {
+            assert_eq!(cache.path(path), None, "{path}");
+        }
+        assert_eq!(cache.path("/data/realtime2/46014.txt"), Some(PathBuf::from("/cache/data/realtime2/46014.txt")));
+        assert_eq!(cache.path("station_history.php?station=46014&x=ü"), Some(PathBuf::from("/cache/station_history.php_station_46014_x__")));
+    }
+
+    #[tokio::test]
+    async fn stores_the_validators_with_the_body() {
+        let dir: PathBuf = temp_dir("roundtrip");
+        let cache = DiskCache::new(&dir);
+        let entry = DiskCacheEntry { body: b"#YY\n\n2019\n".to_vec(), etag: Some("\"abc\"".to_string()), last_modified: None };
+
+        cache.write("data/realtime2/46014.txt", &entry).await;
+        let read: DiskCacheEntry = cache.read("data/realtime2/46014.txt").await.unwrap();
+
+        assert_eq!((read.body, read.etag, read.last_modified), (entry.body, entry.etag, None));
+        assert!(!dir.join("data/realtime2/46014.txt.tmp").exists());
+
+        // a body without the header (eg. from the former separate .meta layout) is a miss
+        std::fs::write(dir.join("data/realtime2/46014.txt"), "#YY\n2019\n").unwrap();
+        assert!(cache.read("data/realtime2/46014.txt").await.is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn revalidates_files_and_serves_old_archives_from_disk() {
+        let dir: PathBuf = temp_dir("client");
+        let server = TestServer::start();
+        server.route("/data/realtime2/46014.txt", TestResponse::ok("#YY  MM DD hh mm WDIR\n2024 10 01 00 00  270\n").with_etag("\"v1\""));
+        let config: NdbcClientConfig = NdbcClientConfig { disk_cache_dir: Some(dir.clone()), ..server.config() };
+        let client = NdbcClient::new(config.clone()).unwrap();
+
+        client.get_station_realtime_stdmet_data("46014").await.unwrap();
+        let res = NdbcClient::new(config).unwrap().get_station_realtime_stdmet_data("46014").await.unwrap();
+
+        let requests = server.requests("/data/realtime2/46014.txt");
+        assert_eq!(res[0].wdir, Some(270.0));
+        assert_eq!((requests.len(), requests[0].header("if-none-match"), requests[1].header("if-none-match")), (2, None, Some("\"v1\"")));
+
+        // last year's archive is never requested again once it is on disk
+        let archive: String = format!("data/historical/stdmet/46014h{}.txt.gz", Utc::now().year() - 1);
+        DiskCache::new(&dir).write(&archive, &DiskCacheEntry { body: gzip("#YY  MM DD hh mm WDIR\n2019 01 01 00 00  180\n").await, etag: None, last_modified: None }).await;
+        let file = StationHistoricFile::new("46014", StationDataType::StandardMeteorological, &(Utc::now().year() - 1).to_string());
+        let res = client.get_historic_file(&file, |body| parse_stdmet("46014", body)).await.unwrap();
+
+        assert_eq!(res[0].wdir, Some(180.0));
+        assert_eq!(server.requests(&("/".to_string() + &archive)).len(), 0);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,185 @@
+#[cfg(feature = "server")]
+use actix_web::{http::StatusCode, HttpResponse, ResponseError};
+#[cfg(feature = "server")]
+use serde::Serialize;
+use std::fmt;
+use std::sync::Arc;
+
+/// Everything that can go wrong while fetching or parsing an NDBC document.
+#[derive(Debug, Clone)]
+pub enum NdbcError {
+    UpstreamUnreachable { url: String, source: Arc<reqwest::Error> },
+    UpstreamNotFound { url: String },
+    UpstreamStatus { url: String, status: u16 },
+    MalformedDocument { url: String, reason: String },
+    MalformedRow { line: usize, column: String, value: String },
+    ColumnCount { line: usize, expected: usize, found: usize },
+    MissingColumn { column: String },
+    UnknownStation { station: String },
+    UnsupportedDataType { data_type: String },
+    InvalidQuery { parameter: String, value: String },
+    Unauthorized,
+}
+
+#[cfg(feature = "server")]
+#[derive(Debug, Serialize)]
+struct NdbcErrorResponse {
+    error: &'static str,
+    message: String,
+}
+
+impl NdbcError {
+    /// A short machine readable name for the kind of error (eg. `not_found`).
+    pub fn kind(&self) -> &'static str {
+        match self {
+            NdbcError::UpstreamUnreachable { .. } => "upstream_unreachable",
+            NdbcError::UpstreamNotFound { .. } => "not_found",
+            NdbcError::UpstreamStatus { .. } => "upstream_error",
+            NdbcError::MalformedDocument { .. } => "malformed_document",
+            NdbcError::MalformedRow { .. } => "malformed_row",
+            NdbcError::ColumnCount { .. } => "malformed_row",
+            NdbcError::MissingColumn { .. } => "missing_column",
+            NdbcError::UnknownStation { .. } => "unknown_station",
+            NdbcError::UnsupportedDataType { .. } => "unsupported_data_type",
+            NdbcError::InvalidQuery { .. } => "invalid_query",
+            NdbcError::Unauthorized => "unauthorized",
+        }
+    }
+
+    /// Whether the request that failed is worth retrying.
+    pub fn is_transient(&self) -> bool {
+        // connection failures, timeouts, throttling and gateway errors are worth another attempt, anything else would fail the same way
+        matches!(self, NdbcError::UpstreamUnreachable { .. } | NdbcError::UpstreamStatus { status: 429 | 500 | 502 | 503 | 504, .. })
+    }
+
+    pub(crate) fn from_reqwest(url: &str, source: reqwest::Error) -> Self {
+        // reqwest only reports a status for errors raised by error_for_status, everything else is a transport failure
+        match source.status() {
+            Some(reqwest::StatusCode::NOT_FOUND) => NdbcError::UpstreamNotFound { url: url.to_string() },
+            Some(status) => NdbcError::UpstreamStatus { url: url.to_string(), status: status.as_u16() },
+            None => NdbcError::UpstreamUnreachable { url: url.to_string(), source: Arc::new(source) },
+        }
+    }
+}
+
+impl fmt::Display for NdbcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NdbcError::UpstreamUnreachable { url, source } => write!(f, "NDBC could not be reached at {url}: {source}"),
+            NdbcError::UpstreamNotFound { url } => write!(f, "NDBC has no resource at {url}"),
+            NdbcError::UpstreamStatus { url, status } => write!(f, "NDBC responded with status {status} for {url}"),
+            NdbcError::MalformedDocument { url, reason } => write!(f, "NDBC returned a malformed document from {url}: {reason}"),
+            NdbcError::MalformedRow { line, column, value } => write!(f, "malformed value {value:?} for column {column} on line {line}"),
+            NdbcError::ColumnCount { line, expected, found } => write!(f, "expected {expected} columns but found {found} on line {line}"),
+            NdbcError::MissingColumn { column } => write!(f, "required column {column} is missing from the header"),
+            NdbcError::UnknownStation { station } => write!(f, "unknown station {station}"),
+            NdbcError::UnsupportedDataType { data_type } => write!(f, "unsupported data type {data_type}"),
+            NdbcError::InvalidQuery { parameter, value } => write!(f, "invalid value {value:?} for query parameter {parameter}"),
+            NdbcError::Unauthorized => write!(f, "a valid admin token is required"),
+        }
+    }
+}
+
+impl std::error::Error for NdbcError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            NdbcError::UpstreamUnreachable { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+impl ResponseError for NdbcError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            NdbcError::UpstreamUnreachable { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            NdbcError::UpstreamNotFound { .. } => StatusCode::NOT_FOUND,
+            NdbcError::UpstreamStatus { status, .. } if *status == 503 || *status == 429 => StatusCode::SERVICE_UNAVAILABLE,
+            NdbcError::UpstreamStatus { .. } => StatusCode::BAD_GATEWAY,
+            NdbcError::MalformedDocument { .. } => StatusCode::BAD_GATEWAY,
+            NdbcError::MalformedRow { .. } => StatusCode::BAD_GATEWAY,
+            NdbcError::ColumnCount { .. } => StatusCode::BAD_GATEWAY,
+            NdbcError::MissingColumn { .. } => StatusCode::BAD_GATEWAY,
+            NdbcError::UnknownStation { .. } => StatusCode::NOT_FOUND,
+            NdbcError::UnsupportedDataType { .. } => StatusCode::BAD_REQUEST,
+            NdbcError::InvalidQuery { .. } => StatusCode::BAD_REQUEST,
+            NdbcError::Unauthorized => StatusCode::UNAUTHORIZED,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code()).json(NdbcErrorResponse { error: self.kind(), message: self.to_string() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ndbc::client::{NdbcClient, NdbcClientConfig};
+    use crate::ndbc::test_server::{TestResponse, TestServer};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn maps_upstream_statuses_to_errors() {
+        let server = TestServer::start();
+        server.route("/data/realtime2/46014.txt", TestResponse::status(404));
+        server.route("/data/realtime2/46015.txt", TestResponse::status(403));
+        let client = NdbcClient::new(server.config()).unwrap();
+
+        let missing = client.get_station_realtime_stdmet_data("46014").await.unwrap_err();
+        let forbidden = client.get_station_realtime_stdmet_data("46015").await.unwrap_err();
+
+        assert!(matches!(&missing, NdbcError::UpstreamNotFound { url } if url.ends_with("/data/realtime2/46014.txt")));
+        assert!(matches!(forbidden, NdbcError::UpstreamStatus { status: 403, .. }));
+        assert_eq!(missing.kind(), "not_found");
+    }
+
+    #[test]
+    fn retries_throttling_and_gateway_errors_only() {
+        let status = |status: u16| NdbcError::UpstreamStatus { url: "u".to_string(), status };
+
+        for code in [429, 500, 502, 503, 504] {
+            assert!(status(code).is_transient(), "{code}");
+        }
+        for code in [400, 403, 501] {
+            assert!(!status(code).is_transient(), "{code}");
+        }
+        assert!(!NdbcError::UpstreamNotFound { url: "u".to_string() }.is_transient());
+        assert!(!NdbcError::MalformedDocument { url: "u".to_string(), reason: "r".to_string() }.is_transient());
+    }
+
+    #[tokio::test]
+    async fn retries_timeouts_and_server_errors_but_not_missing_files() {
+        let server = TestServer::start();
+        server.route("/data/realtime2/46014.txt", TestResponse::status(503));
+        server.route("/data/realtime2/46015.txt", TestResponse::status(404));
+        server.route("/data/realtime2/46016.txt", TestResponse::ok("").with_delay(Duration::from_millis(500)));
+        let client = NdbcClient::new(NdbcClientConfig { timeout: Duration::from_millis(50), ..server.config() }).unwrap();
+
+        let unavailable = client.get_station_realtime_stdmet_data("46014").await.unwrap_err();
+        let missing = client.get_station_realtime_stdmet_data("46015").await.unwrap_err();
+        let timeout = client.get_station_realtime_stdmet_data("46016").await.unwrap_err();
+
+        assert!(matches!(unavailable, NdbcError::UpstreamStatus { status: 503, .. }));
+        assert!(matches!(&timeout, NdbcError::UpstreamUnreachable { source, .. } if source.is_timeout()) && timeout.is_transient());
+        assert!(!missing.is_transient());
+        // the test server's config allows two retries
+        assert_eq!([server.requests("/data/realtime2/46014.txt").len(), server.requests("/data/realtime2/46015.txt").len(), server.requests("/data/realtime2/46016.txt").len()], [3, 1, 3]);
+    }
+
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn responds_with_the_status_and_a_json_body() {
+        let cases: [(NdbcError, StatusCode); 6] = [(NdbcError::UpstreamNotFound { url: "u".to_string() }, StatusCode::NOT_FOUND), (NdbcError::UpstreamStatus { url: "u".to_string(), status: 503 }, StatusCode::SERVICE_UNAVAILABLE), (NdbcError::UpstreamStatus { url: "u".to_string(), status: 500 }, StatusCode::BAD_GATEWAY), (NdbcError::MalformedRow { line: 3, column: "WDIR".to_string(), value: "x".to_string() }, StatusCode::BAD_GATEWAY), (NdbcError::UnknownStation { station: "ABCDE".to_string() }, StatusCode::NOT_FOUND), (NdbcError::InvalidQuery { parameter: "start".to_string(), value: "x".to_string() }, StatusCode::BAD_REQUEST)];
+
+        for (error, status) in cases {
+            assert_eq!(error.status_code(), status, "{error}");
+        }
+
+        let response = NdbcError::UnknownStation { station: "ABCDE".to_string() }.error_response();
+        let body = actix_web::body::to_bytes(response.into_body()).await.unwrap();
+
+        assert_eq!(body, r#"{"error":"unknown_station","message":"unknown station ABCDE"}"#);
+    }
+}
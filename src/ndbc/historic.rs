@@ -1,190 +1,361 @@
-use super::ndbc_schema::{check_null_string, StationContinuousWindsData, StationDataType, StationHistoricFile, StationMetadata, StationStdMetData, StationsMetadataResponse};
-use chrono::{Datelike, NaiveDateTime, Utc};
+use super::client::{check_station, check_year, Fetch, NdbcClient};
+use super::error::NdbcError;
+use super::ndbc_schema::{Month, StationContinuousWindsData, StationDartData, StationDataType, StationHistoricFile, StationMetadata, StationOceanCurrentData, StationOceanographicData, StationSolarRadiationData, StationSpectralData, StationStdMetData, StationWaterLevelData, StationsMetadataResponse};
+use super::parser::{parse_adcp, parse_cwind, parse_dart, parse_historic_spectral, parse_ocean, parse_srad, parse_stdmet, parse_wlevel};
+use futures::{stream, StreamExt, TryStreamExt};
 use log::debug;
 use regex::Regex;
 use serde_xml_rs::from_str;
 
-pub async fn get_stations_metadata() -> Result<Vec<StationMetadata>, Box<dyn std::error::Error>> {
-    // This function returns the historical station metadata for all stations on the NDBC.
-    // This function is not currently used, and is only provided to ensure parity with source system.
-    debug!("called get_stations_metadata");
+impl NdbcClient {
+    /// Returns the historical metadata (deployments, positions, payloads) for every station.
+    pub async fn get_stations_metadata(&self) -> Result<Vec<StationMetadata>, NdbcError> {
+        // This function returns the historical station metadata for all stations on the NDBC.
+        debug!("called get_stations_metadata");
 
-    let url: &str = "https://www.ndbc.noaa.gov/metadata/stationmetadata.xml";
-    debug!("url {}", &url);
+        let url: String = self.url("metadata/stationmetadata.xml");
+        debug!("url {}", &url);
 
-    let body = reqwest::get(url).await?.text().await?;
+        let document_url: String = url.clone();
+        let res: Vec<StationMetadata> = self.get_parsed(&url, Fetch::Index, move |body| from_str::<StationsMetadataResponse>(body).map(|r| r.stations).map_err(|e| NdbcError::MalformedDocument { url: document_url, reason: e.to_string() })).await?;
 
-    let res = from_str::<StationsMetadataResponse>(body.as_str()).unwrap();
+        Ok(res)
+    }
 
-    Ok(res.stations)
-}
+    /// Returns the deployment history of a single station, including stations that are no longer active.
+    pub async fn get_station_metadata(&self, station: &str) -> Result<StationMetadata, NdbcError> {
+        // This function looks the station up in stationmetadata.xml, which is cached, so repeated lookups do not download it again.
+        debug!("called get_station_metadata");
 
-pub async fn get_station_available_downloads(station: &str, data_type: StationDataType) -> Result<Vec<StationHistoricFile>, Box<dyn std::error::Error>> {
-    // This function returns a list of historic files for the given station and data_type (eg. stdmet, cwind, swden)
-    // Please use get_historic_files for bulk lookup (and filter the desired stations) to avoid spamming the resource.
-    debug!("called get_station_available_downloads");
+        let station: String = station.to_uppercase();
+        let res: Option<StationMetadata> = self.get_stations_metadata().await?.into_iter().find(|s: &StationMetadata| s.id == station);
 
-    let url: String = format!("https://www.ndbc.noaa.gov/station_history.php?station={station}");
-    let mut re = Regex::new(("".to_string() + r###"<a href="/download_data\.php\?filename=(.{5,25})\.(.{2,25})\&dir=data/historical/"### + data_type.as_str() + r###"/">(.{1,6})</a>"###).as_str()).unwrap();
-    debug!("url {}", &url);
-    debug!("re {}", &re);
-    let body = reqwest::get(url).await?.text().await?;
+        res.ok_or(NdbcError::UnknownStation { station })
+    }
 
-    let mut res: Vec<StationHistoricFile> = re.captures_iter(&body).map(|c| c.extract()).map(|(_, [f, t, y])| StationHistoricFile { filename: f.to_string() + "." + t, station: f.to_string(), data_type: data_type.clone(), year: y.to_string() }).collect();
+    /// Returns the historic archives NDBC lists for a single station and data type.
+    pub async fn get_station_available_downloads(&self, station: &str, data_type: StationDataType) -> Result<Vec<StationHistoricFile>, NdbcError> {
+        // This function returns a list of historic files for the given station and data_type (eg. stdmet, cwind, swden)
+        // Please use get_historic_files for bulk lookup (and filter the desired stations) to avoid spamming the resource.
+        debug!("called get_station_available_downloads");
 
-    re = Regex::new(("".to_string() + r###"<a href="/download_data\.php\?filename=(.{5,25})\.(.{2,25})\&dir=data/"### + data_type.as_str() + r###"/(Jan|Feb|Mar|Apr|May|Jun|Jul|Aug|Sep|Oct|Nov|Dec)/">(.{1,6})</a>"###).as_str()).unwrap();
-    res.extend(re.captures_iter(&body).map(|c| c.extract()).map(|(_, [f, t, m, _])| StationHistoricFile { filename: f.to_string() + "." + t, station: f.to_string(), data_type: data_type.clone(), year: m.to_string() }));
-    // the data for the current year is quality controlled separately from historic data
+        if data_type == StationDataType::Unsupported {
+            return Err(NdbcError::UnsupportedDataType { data_type: data_type.as_str().to_string() });
+        }
 
-    Ok(res)
-}
+        check_station(station)?;
 
-pub async fn get_historic_files(data_type: StationDataType) -> Result<Vec<StationHistoricFile>, Box<dyn std::error::Error>> {
-    // This function returns a list of all downloadable historic files for a specified data_type (eg. stdmet, cwind, swden)
-    debug!("called get_historic_files");
-
-    let mut url: String = "".to_string() + "https://www.ndbc.noaa.gov/data/historical/" + data_type.as_str();
-    let re = Regex::new(r###"<tr><td valign="top"><img src="/icons/compressed.gif" alt="\[   \]"></td><td><a href="(.{5,50})">(.{5,50})</a></td><td align="right">(.{5,50})</td><td align="right">(.{1,50})</td><td>(.{1,50})</td></tr>"###).unwrap();
-    debug!("url {}", &url);
-
-    let mut body = reqwest::get(url).await?.text().await?;
-
-    let mut res: Vec<StationHistoricFile> = re.captures_iter(&body).map(|c| c.extract()).map(|(_, [f, _, _, _, _])| StationHistoricFile { filename: f.to_string(), station: f[0..=4].to_string().to_uppercase(), data_type: data_type.clone(), year: f[6..=9].to_string() }).collect();
-
-    for i in 1..=12 {
-        url = "".to_string() + "https://www.ndbc.noaa.gov/data/" + data_type.as_str() + "/" +
-        match i { 
-            1 => "Jan",
-            2 => "Feb",
-            3 => "Mar",
-            4 => "Apr",
-            5 => "May",
-            6 => "Jun",
-            7 => "Jul",
-            8 => "Aug",
-            9 => "Sep",
-            10 => "Oct",
-            11 => "Nov",
-            12 => "Dec",
-            _ => "Error",
-        } 
-        + "/";
-        body = reqwest::get(url).await?.text().await?;
-        res.extend(re.captures_iter(&body).map(|c| c.extract()).map(|(_, [f, _, _, _, _])| StationHistoricFile { filename: f.to_string(), station: f[0..=4].to_string().to_uppercase(), data_type: data_type.clone(), year: match i { 
-            1 => "Jan".to_string(),
-            2 => "Feb".to_string(),
-            3 => "Mar".to_string(),
-            4 => "Apr".to_string(),
-            5 => "May".to_string(),
-            6 => "Jun".to_string(),
-            7 => "Jul".to_string(),
-            8 => "Aug".to_string(),
-            9 => "Sep".to_string(),
-            10 => "Oct".to_string(),
-            11 => "Nov".to_string(),
-            12 => "Dec".to_string(),
-            _ => "Error".to_string(),
-        } }));
-
-    }
-    
-    Ok(res)
-}
+        let url: String = self.url(&format!("station_history.php?station={station}"));
+        let mut re = Regex::new(("".to_string() + r###"<a href="/download_data\.php\?filename=(.{5,25})\.(.{2,25})\&dir=data/historical/"### + data_type.as_str() + r###"/">(.{1,6})</a>"###).as_str()).unwrap();
+        debug!("url {}", &url);
+        debug!("re {}", &re);
+        // the page lists every data_type, so only the page itself is shared between concurrent lookups
+        let body: String = self.get_parsed(&url, Fetch::Index, |body| Ok(body.to_string())).await?;
+
+        let mut res: Vec<StationHistoricFile> = re.captures_iter(&body).map(|c| c.extract()).map(|(_, [f, t, y])| StationHistoricFile { filename: f.to_string() + "." + t, station: station.to_uppercase(), data_type: data_type.clone(), year: y.to_string() }).collect();
+
+        re = Regex::new(("".to_string() + r###"<a href="/download_data\.php\?filename=(.{5,25})\.(.{2,25})\&dir=data/"### + data_type.as_str() + "/(" + &Month::ALL.map(|m| m.as_str()).join("|") + r###")/">(.{1,6})</a>"###).as_str()).unwrap();
+        res.extend(re.captures_iter(&body).map(|c| c.extract()).map(|(_, [f, t, m, _])| StationHistoricFile { filename: f.to_string() + "." + t, station: station.to_uppercase(), data_type: data_type.clone(), year: m.to_string() }));
+        // the data for the current year is quality controlled separately from historic data
+
+        Ok(res)
+    }
+
+    /// Returns the historic archives for every station for a data type, including this year's monthly files.
+    pub async fn get_historic_files(&self, data_type: StationDataType) -> Result<Vec<StationHistoricFile>, NdbcError> {
+        // This function returns a list of all downloadable historic files for a specified data_type (eg. stdmet, cwind, swden)
+        debug!("called get_historic_files");
+
+        if data_type == StationDataType::Unsupported {
+            return Err(NdbcError::UnsupportedDataType { data_type: data_type.as_str().to_string() });
+        }
+
+        let re = Regex::new(r###"<tr><td valign="top"><img src="/icons/compressed.gif" alt="\[   \]"></td><td><a href="(.{5,50})">(.{5,50})</a></td><td align="right">(.{5,50})</td><td align="right">(.{1,50})</td><td>(.{1,50})</td></tr>"###).unwrap();
+
+        // the yearly archive and the monthly directories for the current year are independent, so they are crawled concurrently
+        let mut directories: Vec<(String, Option<Month>)> = vec![("data/historical/".to_string() + data_type.as_str() + "/", None)];
+        directories.extend(Month::ALL.into_iter().map(|m| ("data/".to_string() + data_type.as_str() + "/" + m.as_str() + "/", Some(m))));
+
+        let listings: Vec<Vec<StationHistoricFile>> = stream::iter(directories)
+            .map(|(path, month)| {
+                let re: Regex = re.clone();
+                let data_type: StationDataType = data_type.clone();
+                let url: String = self.url(&path);
+                debug!("url {}", &url);
+
+                async move {
+                    self.get_parsed(&url, Fetch::Index, move |body| {
+                        Ok(re
+                            .captures_iter(body)
+                            .map(|c| c.extract())
+                            .filter_map(|(_, [f, _, _, _, _])| {
+                                // a name too short to hold a station and a year is not an archive, it is skipped rather than sliced
+                                let station: &str = f.get(0..=4)?;
+                                let year: &str = match month {
+                                    Some(m) => m.as_str(),
+                                    None => f.get(6..=9)?,
+                                };
+
+                                Some(StationHistoricFile { filename: f.to_string(), station: station.to_uppercase(), data_type: data_type.clone(), year: year.to_string() })
+                            })
+                            .collect())
+                    })
+                    .await
+                }
+            })
+            .buffered(self.crawl_concurrency())
+            .try_collect()
+            .await?;
+
+        let res: Vec<StationHistoricFile> = listings.concat();
+
+        Ok(res)
+    }
+
+    /// Downloads, decompresses and parses a file listed by [`NdbcClient::get_historic_files`] or [`NdbcClient::get_station_available_downloads`] (eg. with [`parse_stdmet`]).
+    pub async fn get_historic_file<T, P>(&self, file: &StationHistoricFile, parse: P) -> Result<T, NdbcError>
+    where
+        T: Clone + Send + Sync + 'static,
+        P: FnOnce(&str) -> Result<T, NdbcError> + Send + 'static,
+    {
+        // This function downloads the archive as gzip and decompresses it while it streams in.
+        // Concurrent downloads of a file only share the parsed result when they use the same parser.
+        debug!("called get_historic_file");
+
+        let url: String = self.url(&file.path());
+        debug!("url {}", &url);
+
+        self.get_parsed(&url, Fetch::Gzip, parse).await
+    }
+
+    async fn find_historic_file(&self, station: &str, data_type: StationDataType, year: &str) -> Result<StationHistoricFile, NdbcError> {
+        // This function picks the archive for a year (eg. 2019) or a month of the current year (eg. Dec).
+        // A monthly file is named after the year it belongs to, which is only known from the station's listing (a Dec file listed in January is last year's).
+        check_station(station)?;
+        check_year(year)?;
+
+        if Month::from_abbreviation(year).is_none() {
+            return Ok(StationHistoricFile::new(station, data_type, year));
+        }
+
+        let url: String = self.url(&("data/".to_string() + data_type.as_str() + "/" + year + "/"));
+        let res: Option<StationHistoricFile> = self.get_station_available_downloads(station, data_type).await?.into_iter().find(|f: &StationHistoricFile| f.year == year);
+
+        res.ok_or(NdbcError::UpstreamNotFound { url })
+    }
+
+    /// Returns the quality controlled standard meteorological data for a station and year (or month of the current year, eg. `Jan`).
+    pub async fn get_station_historical_stdmet_data(&self, station: &str, year: &str) -> Result<Vec<StationStdMetData>, NdbcError> {
+        // This function returns the historic raw stdmet sensor data for a given station over a given year.
+        debug!("called get_station_historical_stdmet_data");
+
+        let file: StationHistoricFile = self.find_historic_file(station, StationDataType::StandardMeteorological, year).await?;
+
+        let station: String = station.to_uppercase();
+        let res = self.get_historic_file(&file, move |body| parse_stdmet(&station, body)).await?;
+
+        Ok(res)
+    }
+
+    /// Returns the quality controlled continuous winds data for a station and year (or month of the current year).
+    pub async fn get_station_historical_cwind_data(&self, station: &str, year: &str) -> Result<Vec<StationContinuousWindsData>, NdbcError> {
+        // This function returns the historic raw cwind sensor data for a given station over a given year.
+        debug!("called get_station_historical_cwind_data");
+
+        let file: StationHistoricFile = self.find_historic_file(station, StationDataType::ContinuousWinds, year).await?;
+
+        let station: String = station.to_uppercase();
+        let res = self.get_historic_file(&file, move |body| parse_cwind(&station, body)).await?;
+
+        Ok(res)
+    }
+
+    /// Returns the quality controlled solar radiation data for a station and year (or month of the current year).
+    pub async fn get_station_historical_srad_data(&self, station: &str, year: &str) -> Result<Vec<StationSolarRadiationData>, NdbcError> {
+        // This function returns the historic shortwave and longwave radiation for a given station over a given year.
+        debug!("called get_station_historical_srad_data");
+
+        let file: StationHistoricFile = self.find_historic_file(station, StationDataType::SolarRadiation, year).await?;
+
+        let station: String = station.to_uppercase();
+        let res = self.get_historic_file(&file, move |body| parse_srad(&station, body)).await?;
+
+        Ok(res)
+    }
+
+    /// Returns the quality controlled DART water column height for a station and year (or month of the current year).
+    pub async fn get_station_historical_dart_data(&self, station: &str, year: &str) -> Result<Vec<StationDartData>, NdbcError> {
+        // This function returns the historic tsunameter water column height for a given station over a given year.
+        debug!("called get_station_historical_dart_data");
+
+        let file: StationHistoricFile = self.find_historic_file(station, StationDataType::Dart, year).await?;
+
+        let station: String = station.to_uppercase();
+        let res = self.get_historic_file(&file, move |body| parse_dart(&station, body)).await?;
 
-pub async fn get_station_historical_stdmet_data(station: &str, year: &str) -> Result<Vec<StationStdMetData>, Box<dyn std::error::Error>> {
-    // This function returns the historic raw stdmet sensor data for a given station over a given year.
-    debug!("called get_station_historical_stdmet_data");
-    
-    let mut url: String = "".to_string()
-        + "https://www.ndbc.noaa.gov/view_text_file.php?filename="
-        + station.to_lowercase().as_str() // filenames are in lower-case and case sensitive
-        + "h"
-        + year
-        + ".txt.gz"
-        + "&dir=data/historical/"
-        + StationDataType::StandardMeteorological.as_str()
-        + "/";
-    let re = Regex::new(&(r"([0-9M\+\.-]+)[\s\n]+".repeat(18))).unwrap();
-
-    // this assumes NOAA compiles the annual reports for each buoy by the first day of the new year (unlikely)
-    // this means this will likely not work at the start of a new year, until they compile the historic report
-    // we could only avoid this by using the historic files for reference
-    if year.chars().any(|c| c.is_alphabetic()) { // if year contains non-numeric character, then it must be a month of the year (supports mid-year historic data lookup)
-        url = "".to_string()
-        + "https://www.ndbc.noaa.gov/view_text_file.php?filename="
-        + station.to_lowercase().as_str() // filenames are in lower-case and case sensitive
-        + match year { // in this case year represents a month (supports mid-year historic data)
-            "Jan" => "1",
-            "Feb" => "2",
-            "Mar" => "3",
-            "Apr" => "4",
-            "May" => "5",
-            "Jun" => "6",
-            "Jul" => "7",
-            "Aug" => "8",
-            "Sep" => "9",
-            "Oct" => "10",
-            "Nov" => "11",
-            "Dec" => "12",
-            _ => "13"
+        Ok(res)
+    }
+
+    /// Returns the quality controlled spectra of a spectral data type (swden, swdir, swdir2, swr1, swr2) for a station and year (or month of the current year).
+    pub async fn get_station_historical_spectral_data(&self, station: &str, data_type: StationDataType, year: &str) -> Result<Vec<StationSpectralData>, NdbcError> {
+        // This function returns the historic spectral wave density or one of its directional components for a given station over a given year.
+        debug!("called get_station_historical_spectral_data");
+
+        if !matches!(data_type, StationDataType::SpectralWaveDensity | StationDataType::SpectralWaveA1Density | StationDataType::SpectralWaveA2Density | StationDataType::SpectralWaveR1Density | StationDataType::SpectralWaveR2Density) {
+            return Err(NdbcError::UnsupportedDataType { data_type: data_type.as_str().to_string() });
         }
-        + &Utc::now().year().to_string() // wont work if NOAA is slow to compile annual historic report
-        + ".txt.gz"
-        + "&dir=data/"
-        + StationDataType::StandardMeteorological.as_str()
-        + "/"
-        + year // in this case year represents a month (supports mid-year historic data)
-        + "/";
-    }
-    debug!("url {}", &url);
-
-    let body = reqwest::get(url).await?.text().await?;
-
-    let res = re
-        .captures_iter(&body)
-        .map(|c| c.extract())
-        .map(|(_, [year, month, day, hour, minute, wdir, wspd, gst, wvht, dpd, apd, mwd, pres, atmp, wtmp, dewp, vis, tide])| StationStdMetData {
-            station: station.to_string().to_uppercase(),
-            timestamp: NaiveDateTime::parse_from_str(("".to_string() + year + "-" + month + "-" + day + " " + hour + ":" + minute).as_str(), "%Y-%m-%d %H:%M").unwrap(),
-            wdir: if !check_null_string(wdir) { Some(wdir.parse().unwrap()) } else { None },
-            wspd: if !check_null_string(wspd) { Some(wspd.parse().unwrap()) } else { None },
-            gst: if !check_null_string(gst) { Some(gst.parse().unwrap()) } else { None },
-            wvht: if !check_null_string(wvht) { Some(wvht.parse().unwrap()) } else { None },
-            dpd: if !check_null_string(dpd) { Some(dpd.parse().unwrap()) } else { None },
-            apd: if !check_null_string(apd) { Some(apd.parse().unwrap()) } else { None },
-            mwd: if !check_null_string(mwd) { Some(mwd.parse().unwrap()) } else { None },
-            pres: if !check_null_string(pres) { Some(pres.parse().unwrap()) } else { None },
-            atmp: if !check_null_string(atmp) { Some(atmp.parse().unwrap()) } else { None },
-            wtmp: if !check_null_string(wtmp) { Some(wtmp.parse().unwrap()) } else { None },
-            dewp: if !check_null_string(dewp) { Some(dewp.parse().unwrap()) } else { None },
-            vis: if !check_null_string(vis) { Some(vis.parse().unwrap()) } else { None },
-            ptdy: None, // ptdy is not available in historic files
-            tide: if !check_null_string(tide) { Some(tide.parse().unwrap()) } else { None },
-        })
-        .collect();
-
-    Ok(res)
+
+        let file: StationHistoricFile = self.find_historic_file(station, data_type.clone(), year).await?;
+
+        let station: String = station.to_uppercase();
+        let res = self.get_historic_file(&file, move |body| parse_historic_spectral(&station, data_type, body)).await?;
+
+        Ok(res)
+    }
+
+    /// Returns the quality controlled ocean current profiles for a station and year (or month of the current year).
+    pub async fn get_station_historical_adcp_data(&self, station: &str, year: &str) -> Result<Vec<StationOceanCurrentData>, NdbcError> {
+        // This function returns the historic ocean current speed and direction at each depth bin for a given station over a given year.
+        debug!("called get_station_historical_adcp_data");
+
+        let file: StationHistoricFile = self.find_historic_file(station, StationDataType::OceanCurrent, year).await?;
+
+        let station: String = station.to_uppercase();
+        let res = self.get_historic_file(&file, move |body| parse_adcp(&station, body)).await?;
+
+        Ok(res)
+    }
+
+    /// Returns the quality controlled oceanographic and water quality data for a station and year (or month of the current year).
+    pub async fn get_station_historical_ocean_data(&self, station: &str, year: &str) -> Result<Vec<StationOceanographicData>, NdbcError> {
+        // This function returns the historic ocean temperature, salinity, oxygen, chlorophyll, turbidity, pH and Eh for a given station over a given year.
+        debug!("called get_station_historical_ocean_data");
+
+        let file: StationHistoricFile = self.find_historic_file(station, StationDataType::Oceanographic, year).await?;
+
+        let station: String = station.to_uppercase();
+        let res = self.get_historic_file(&file, move |body| parse_ocean(&station, body)).await?;
+
+        Ok(res)
+    }
+
+    /// Returns the quality controlled water level data for a station and year (or month of the current year).
+    pub async fn get_station_historical_wlevel_data(&self, station: &str, year: &str) -> Result<Vec<StationWaterLevelData>, NdbcError> {
+        // This function returns the historic water level for a given station over a given year.
+        debug!("called get_station_historical_wlevel_data");
+
+        let file: StationHistoricFile = self.find_historic_file(station, StationDataType::WaterLevel, year).await?;
+
+        let station: String = station.to_uppercase();
+        let res = self.get_historic_file(&file, move |body| parse_wlevel(&station, body)).await?;
+
+        Ok(res)
+    }
 }
 
-pub async fn get_station_historical_cwind_data(station: &str, year: &str) -> Result<Vec<StationContinuousWindsData>, Box<dyn std::error::Error>> {
-    // This function returns the historic raw stdmet sensor data for a given station over a given year.
-    debug!("called get_station_historical_cwind_data");
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ndbc::test_server::{gzip, TestResponse, TestServer};
+    use chrono::{NaiveDate, NaiveDateTime};
+    use std::time::Duration;
 
-    let url: String = "".to_string()
-        + "https://www.ndbc.noaa.gov/view_text_file.php?filename="
-        + station.to_lowercase().as_str() // filenames are in lower-case and case sensitive
-        + "c"
-        + year
-        + ".txt.gz"
-        + "&dir=data/historical/"
-        + StationDataType::ContinuousWinds.as_str()
-        + "/";
-    let re = Regex::new(&(r"([0-9M\+\.-]+)[\s\n]+".repeat(10))).unwrap();
-    debug!("url {}", &url);
+    const STDMET: &str = "#YY  MM DD hh mm WDIR WSPD\n#yr  mo dy hr mn degT m/s\n2025 12 31 23 50  270  5.0\n";
 
-    let body = reqwest::get(url).await?.text().await?;
+    #[tokio::test]
+    async fn downloads_and_decompresses_yearly_archives() {
+        let server = TestServer::start();
+        server.route("/data/historical/stdmet/46014h2019.txt.gz", TestResponse::ok(gzip(STDMET).await));
+        let client = NdbcClient::new(server.config()).unwrap();
+
+        let res = client.get_station_historical_stdmet_data("46014", "2019").await.unwrap();
+
+        assert_eq!((res.len(), res[0].wspd), (1, Some(5.0)));
+    }
 
-    let res = re.captures_iter(&body).map(|c| c.extract()).map(|(_, [year, month, day, hour, minute, wdir, wspd, gdr, gst, _gtime])| StationContinuousWindsData { station: station.to_string().to_uppercase(), timestamp: NaiveDateTime::parse_from_str(("".to_string() + year + "-" + month + "-" + day + " " + hour + ":" + minute).as_str(), "%Y-%m-%d %H:%M").unwrap(), wdir: if !check_null_string(wdir) { Some(wdir.parse().unwrap()) } else { None }, wspd: if !check_null_string(wspd) { Some(wspd.parse().unwrap()) } else { None }, gdr: if !check_null_string(gdr) { Some(gdr.parse().unwrap()) } else { None }, gst: if !check_null_string(gst) { Some(gst.parse().unwrap()) } else { None } }).collect();
+    #[tokio::test]
+    async fn rejects_invalid_stations_and_years_before_fetching() {
+        let server = TestServer::start();
+        let client = NdbcClient::new(server.config()).unwrap();
 
-    Ok(res)
+        // a fetch would have failed with a 404 from the test server instead
+        assert!(matches!(client.get_station_historical_wlevel_data("../46014", "2019").await, Err(NdbcError::InvalidQuery { parameter, .. }) if parameter == "station"));
+        assert!(matches!(client.get_station_historical_adcp_data("42887", "2019/../x").await, Err(NdbcError::InvalidQuery { parameter, .. }) if parameter == "year"));
+        assert!(matches!(client.get_station_available_downloads("46014&x=1", StationDataType::Oceanographic).await, Err(NdbcError::InvalidQuery { parameter, .. }) if parameter == "station"));
+        assert!(matches!(client.get_station_realtime_ocean_data("46014.txt").await, Err(NdbcError::InvalidQuery { parameter, .. }) if parameter == "station"));
+    }
+
+    #[tokio::test]
+    async fn reads_deployments_from_the_station_metadata() {
+        let server = TestServer::start();
+        server.route("/metadata/stationmetadata.xml", TestResponse::ok(r#"<stations created="2024-10-01T00:00:00UTC"><station id="46014" name="PT ARENA" owner="NDBC" pgm="NDBC Meteorological/Ocean" type="buoy"><history start="1981-09-01" stop="1982-02-11 13:00:00" lat="39.22" lng="-123.97" elev="0" met="y" hull="10D" anemom_height="" /><history start="2019-06-05" stop="" lat="" lng="-123.96" elev="0" met="y" hull="3D" anemom_height="3.8" /></station></stations>"#));
+        let client = NdbcClient::new(server.config()).unwrap();
+
+        let res: StationMetadata = client.get_station_metadata("46014").await.unwrap();
+
+        assert_eq!((res.id.as_str(), res.name.as_deref(), res.history.len()), ("46014", Some("PT ARENA"), 2));
+        // a time after the date is dropped, empty attributes are unknown rather than errors
+        assert_eq!((res.history[0].start, res.history[0].stop), (NaiveDate::from_ymd_opt(1981, 9, 1), NaiveDate::from_ymd_opt(1982, 2, 11)));
+        assert_eq!((res.history[0].lat, res.history[0].anemom_height), (Some(39.22), None));
+        assert_eq!((res.history[1].stop, res.history[1].lat, res.history[1].anemom_height), (None, None, Some(3.8)));
+        assert!(matches!(client.get_station_metadata("41001").await, Err(NdbcError::UnknownStation { station }) if station == "41001"));
+    }
+
+    fn count_rows(body: &str) -> Result<usize, NdbcError> {
+        Ok(body.lines().filter(|l| !l.starts_with('#')).count())
+    }
+
+    fn count_bytes(body: &str) -> Result<usize, NdbcError> {
+        Ok(body.len())
+    }
+
+    #[tokio::test]
+    async fn only_shares_a_download_between_callers_with_the_same_parser() {
+        let server = TestServer::start();
+        server.route("/data/historical/stdmet/46014h2019.txt.gz", TestResponse::ok(gzip(STDMET).await).with_delay(Duration::from_millis(50)));
+        let client = NdbcClient::new(server.config()).unwrap();
+        let file: StationHistoricFile = StationHistoricFile::new("46014", StationDataType::StandardMeteorological, "2019");
+
+        let (a, b, c) = futures::join!(client.get_historic_file(&file, count_rows), client.get_historic_file(&file, count_rows), client.get_historic_file(&file, count_bytes));
+
+        assert_eq!((a.unwrap(), b.unwrap(), c.unwrap()), (1, 1, STDMET.len()));
+        assert_eq!(server.requests("/data/historical/stdmet/46014h2019.txt.gz").len(), 2);
+    }
+
+    fn listing(names: &[&str]) -> String {
+        names.iter().map(|n| format!(r#"<tr><td valign="top"><img src="/icons/compressed.gif" alt="[   ]"></td><td><a href="{n}">{n}</a></td><td align="right">2025-03-02 10:01  </td><td align="right">123K</td><td>&nbsp;</td></tr>"#) + "\n").collect()
+    }
+
+    #[tokio::test]
+    async fn crawls_the_yearly_and_monthly_directories() {
+        let server = TestServer::start();
+        server.route("/data/historical/stdmet/", TestResponse::ok(listing(&["46014h2019.txt.gz", "41001h2020.txt.gz", "readme.gz"])));
+        for month in Month::ALL {
+            server.route(&format!("/data/stdmet/{}/", month.as_str()), TestResponse::ok(listing(if month == Month::Jan { &["4601412026.txt.gz"] } else { &[] })));
+        }
+        let client = NdbcClient::new(server.config()).unwrap();
+
+        let res = client.get_historic_files(StationDataType::StandardMeteorological).await.unwrap();
+        let files: Vec<(&str, &str, &str)> = res.iter().map(|f| (f.filename.as_str(), f.station.as_str(), f.year.as_str())).collect();
+
+        assert_eq!(files, vec![("46014h2019.txt.gz", "46014", "2019"), ("41001h2020.txt.gz", "41001", "2020"), ("4601412026.txt.gz", "46014", "Jan")]);
+        assert_eq!(res[2].path(), "data/stdmet/Jan/4601412026.txt.gz");
+    }
+
+    #[tokio::test]
+    async fn finds_last_years_december_file_in_january() {
+        // in January the December file still belongs to last year, the year is only known from the listed file name
+        let server = TestServer::start();
+        server.route("/station_history.php?station=46014", TestResponse::ok(r#"<a href="/download_data.php?filename=46014c2025.txt.gz&dir=data/stdmet/Dec/">Dec</a> <a href="/download_data.php?filename=4601412026.txt.gz&dir=data/stdmet/Jan/">Jan</a>"#));
+        server.route("/data/stdmet/Dec/46014c2025.txt.gz", TestResponse::ok(gzip(STDMET).await));
+        let client = NdbcClient::new(server.config()).unwrap();
+
+        let files = client.get_station_available_downloads("46014", StationDataType::StandardMeteorological).await.unwrap();
+        let res = client.get_station_historical_stdmet_data("46014", "Dec").await.unwrap();
+
+        let december: NaiveDateTime = NaiveDate::from_ymd_opt(2025, 12, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+        assert_eq!(files[0].period(), Some((december, NaiveDate::from_ymd_opt(2026, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap())));
+        assert_eq!(res[0].timestamp, NaiveDate::from_ymd_opt(2025, 12, 31).unwrap().and_hms_opt(23, 50, 0).unwrap());
+        assert!(matches!(client.get_station_historical_stdmet_data("46014", "Nov").await, Err(NdbcError::UpstreamNotFound { .. })));
+    }
 }
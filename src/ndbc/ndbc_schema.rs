@@ -1,8 +1,10 @@
+use super::error::NdbcError;
 use chrono::{
     prelude::{DateTime, Utc},
-    NaiveDateTime,
+    Duration, Months, NaiveDate, NaiveDateTime, NaiveTime,
 };
 use serde::{de, Deserialize, Serialize};
+use std::str::FromStr;
 
 fn deserialize_bool<'de, D>(deserializer: D) -> Result<Option<bool>, D::Error>
 where
@@ -20,6 +22,27 @@ where
     }
 }
 
+fn deserialize_nullable<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: de::Deserializer<'de>,
+    T: FromStr,
+{
+    // stationmetadata.xml leaves attributes empty when a value is unknown, those (and anything unparseable) become None like deserialize_bool
+    let s: Option<String> = de::Deserialize::deserialize(deserializer).unwrap_or(None);
+
+    Ok(s.and_then(|v| v.trim().parse().ok()))
+}
+
+fn deserialize_date<'de, D>(deserializer: D) -> Result<Option<NaiveDate>, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    // dates are given as YYYY-MM-DD, some with a time appended which is not kept
+    let s: Option<String> = de::Deserialize::deserialize(deserializer).unwrap_or(None);
+
+    Ok(s.and_then(|v| v.trim().get(0..10).and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())))
+}
+
 fn deserialize_string_upper<'de, D>(deserializer: D) -> Result<String, D::Error>
 where
     D: de::Deserializer<'de>,
@@ -29,7 +52,7 @@ where
     Ok(s.to_uppercase())
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
 pub struct Station {
     #[serde(default, deserialize_with = "deserialize_string_upper")]
     pub id: String,
@@ -48,11 +71,79 @@ pub struct Station {
     pub waterquality: Option<bool>,
     #[serde(default, deserialize_with = "deserialize_bool")]
     pub dart: Option<bool>,
+    #[serde(default)]
+    pub active: Option<bool>,
+    pub hull: Option<String>,
+    pub payload: Option<String>,
+    pub timezone: Option<String>,
+    pub forecast: Option<String>,
+    pub note: Option<String>,
     pub stdmet_history: Option<Vec<StationHistoricFile>>,
     pub cwind_history: Option<Vec<StationHistoricFile>>,
+    pub adcp_history: Option<Vec<StationHistoricFile>>,
+    pub ocean_history: Option<Vec<StationHistoricFile>>,
+    pub wlevel_history: Option<Vec<StationHistoricFile>>,
     pub stdmet_realtime: Option<Vec<StationRealtimeFile>>,
     pub cwind_realtime: Option<Vec<StationRealtimeFile>>,
     pub spec_realtime: Option<Vec<StationRealtimeFile>>,
+    pub ocean_realtime: Option<Vec<StationRealtimeFile>>,
+    pub supl_realtime: Option<Vec<StationRealtimeFile>>,
+}
+
+impl Station {
+    pub fn from_metadata(metadata: &StationMetadata) -> Self {
+        // This function describes a station that is no longer in activestations.xml, the position is taken from its latest deployment.
+        let latest: Option<&StationMetadataHistory> = metadata.history.iter().max_by_key(|h: &&StationMetadataHistory| h.start);
+
+        Station { id: metadata.id.to_uppercase(), lat: latest.and_then(|h| h.lat), lon: latest.and_then(|h| h.lng), elev: latest.and_then(|h| h.elev), name: metadata.name.clone(), owner: metadata.owner.clone(), pgm: metadata.pgm.clone(), r#type: metadata.r#type.clone(), active: Some(false), ..Default::default() }
+    }
+
+    /// Copies the hull, payload, time zone, forecast region and note of a station_table.txt row onto the station.
+    pub fn merge_station_table(&mut self, entry: &StationTableEntry) {
+        // station_table.txt fills in what activestations.xml leaves out, the name and owner are only taken when the station has none
+        self.hull = entry.hull.clone();
+        self.payload = entry.payload.clone();
+        self.timezone = entry.time_zone().map(|tz: &str| tz.to_string());
+        self.forecast = entry.forecast.clone();
+        self.note = entry.note.clone();
+
+        if self.name.is_none() {
+            self.name = entry.name.clone();
+        }
+        if self.owner.is_none() {
+            self.owner = entry.owner.clone();
+        }
+    }
+}
+
+/// A row of station_table.txt, NDBC's pipe delimited listing of every station's hull, payload and time zone.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct StationTableEntry {
+    pub id: String,
+    pub owner: Option<String>,
+    pub ttype: Option<String>,
+    pub hull: Option<String>,
+    pub name: Option<String>,
+    pub payload: Option<String>,
+    pub location: Option<String>,
+    pub timezone: Option<String>,
+    pub forecast: Option<String>,
+    pub note: Option<String>,
+}
+
+impl StationTableEntry {
+    /// The IANA time zone name of the station (eg. `America/New_York`), station_table.txt only gives a single letter (eg. `E`).
+    pub fn time_zone(&self) -> Option<&'static str> {
+        // only the US zones have an unambiguous letter, anything else is left unset rather than guessed
+        match self.timezone.as_deref()? {
+            "E" => Some("America/New_York"),
+            "C" => Some("America/Chicago"),
+            "M" => Some("America/Denver"),
+            "P" => Some("America/Los_Angeles"),
+            "H" => Some("Pacific/Honolulu"),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -65,23 +156,31 @@ pub struct ActiveStationsResponse {
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct StationMetadataHistory {
-    pub start: Option<String>,
-    pub stop: Option<String>,
-    pub lat: Option<String>,
-    pub lng: Option<String>,
-    pub elev: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_date")]
+    pub start: Option<NaiveDate>,
+    #[serde(default, deserialize_with = "deserialize_date")]
+    pub stop: Option<NaiveDate>,
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    pub lat: Option<f32>,
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    pub lng: Option<f32>,
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    pub elev: Option<f32>,
     pub met: Option<String>,
     pub hull: Option<String>,
-    pub anemom_height: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    pub anemom_height: Option<f32>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct StationMetadata {
+    #[serde(default, deserialize_with = "deserialize_string_upper")]
     pub id: String,
     pub name: Option<String>,
     pub owner: Option<String>,
     pub pgm: Option<String>,
     pub r#type: Option<String>,
+    #[serde(default)]
     pub history: Vec<StationMetadataHistory>,
 }
 
@@ -99,6 +198,47 @@ pub struct StationHistoricFile {
     pub data_type: StationDataType,
     pub year: String,
 }
+
+impl StationHistoricFile {
+    pub fn new(station: &str, data_type: StationDataType, year: &str) -> Self {
+        // This function builds the archive name for a station, data_type and year (eg. 46014h2019.txt.gz).
+        // The monthly files are named after the year they belong to, which is not known from the month alone (a Dec file listed in January is last year's), so they are only found through get_station_available_downloads.
+        // filenames are in lower-case and case sensitive
+        let filename: String = station.to_lowercase() + data_type.file_code() + year + ".txt.gz";
+
+        StationHistoricFile { filename, station: station.to_uppercase(), data_type, year: year.to_string() }
+    }
+
+    pub fn is_monthly(&self) -> bool {
+        // the monthly files for the current year are listed with the month name in place of the year
+        self.year.chars().any(|c| c.is_alphabetic())
+    }
+
+    pub fn path(&self) -> String {
+        if self.is_monthly() {
+            "data/".to_string() + self.data_type.as_str() + "/" + &self.year + "/" + &self.filename
+        } else {
+            "data/historical/".to_string() + self.data_type.as_str() + "/" + &self.filename
+        }
+    }
+
+    /// The time span the archive covers, from the start of its year (or month) up to but excluding the next.
+    pub fn period(&self) -> Option<(NaiveDateTime, NaiveDateTime)> {
+        // the year is read from the filename (eg. 46014h2019.txt.gz, or 4601412024.txt.gz for January 2024), so a December file listed in January is not mistaken for this year
+        let stem: &str = self.filename.split('.').next()?;
+        let year: i32 = stem.get(stem.len().checked_sub(4)?..)?.parse().ok()?;
+
+        let (start, end): (NaiveDate, NaiveDate) = match Month::from_abbreviation(&self.year) {
+            Some(month) => {
+                let start: NaiveDate = NaiveDate::from_ymd_opt(year, month.number(), 1)?;
+                (start, start.checked_add_months(Months::new(1))?)
+            }
+            None => (NaiveDate::from_ymd_opt(year, 1, 1)?, NaiveDate::from_ymd_opt(year + 1, 1, 1)?),
+        };
+
+        Some((start.and_time(NaiveTime::MIN), end.and_time(NaiveTime::MIN)))
+    }
+}
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct StationRealtimeFile {
     pub filename: String,
@@ -106,6 +246,14 @@ pub struct StationRealtimeFile {
     pub data_type: StationDataType,
     pub timestamp: NaiveDateTime,
 }
+
+impl StationRealtimeFile {
+    /// The realtime files hold the last 45 days of observations up to when the file was last modified.
+    pub fn period(&self) -> (NaiveDateTime, NaiveDateTime) {
+        (self.timestamp - Duration::days(45), self.timestamp)
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 pub enum StationDataType {
     StandardMeteorological,
@@ -118,6 +266,11 @@ pub enum StationDataType {
     SpectralWaveR1Density,
     SpectralWaveR2Density,
     SolarRadiation,
+    Oceanographic,
+    Supplemental,
+    Rain,
+    Dart,
+    WaterLevel,
     Unsupported,
 }
 
@@ -134,9 +287,101 @@ impl StationDataType {
             StationDataType::SpectralWaveR1Density => "swr1",
             StationDataType::SpectralWaveR2Density => "swr2",
             StationDataType::SolarRadiation => "srad",
+            StationDataType::Oceanographic => "ocean",
+            StationDataType::Supplemental => "supl",
+            StationDataType::Rain => "rain",
+            StationDataType::Dart => "dart",
+            StationDataType::WaterLevel => "wlevel",
             StationDataType::Unsupported => "unsupported",
         }
     }
+
+    pub fn file_code(&self) -> &'static str {
+        // historic files are named <station><code><year>.txt.gz (eg. 46014h2019.txt.gz for stdmet)
+        match self {
+            StationDataType::StandardMeteorological => "h",
+            StationDataType::ContinuousWinds => "c",
+            StationDataType::OceanCurrent => "a",
+            StationDataType::SpectralWaveSummary => "",
+            StationDataType::SpectralWaveDensity => "w",
+            StationDataType::SpectralWaveA1Density => "d",
+            StationDataType::SpectralWaveA2Density => "i",
+            StationDataType::SpectralWaveR1Density => "j",
+            StationDataType::SpectralWaveR2Density => "k",
+            StationDataType::SolarRadiation => "r",
+            StationDataType::Oceanographic => "o",
+            StationDataType::Supplemental => "",
+            StationDataType::Rain => "",
+            StationDataType::Dart => "t",
+            StationDataType::WaterLevel => "l",
+            StationDataType::Unsupported => "",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub enum Month {
+    Jan,
+    Feb,
+    Mar,
+    Apr,
+    May,
+    Jun,
+    Jul,
+    Aug,
+    Sep,
+    Oct,
+    Nov,
+    Dec,
+}
+
+impl Month {
+    pub const ALL: [Month; 12] = [Month::Jan, Month::Feb, Month::Mar, Month::Apr, Month::May, Month::Jun, Month::Jul, Month::Aug, Month::Sep, Month::Oct, Month::Nov, Month::Dec];
+
+    pub fn as_str(&self) -> &'static str {
+        // NDBC names the monthly directories by abbreviation (eg. data/stdmet/Jan/)
+        match self {
+            Month::Jan => "Jan",
+            Month::Feb => "Feb",
+            Month::Mar => "Mar",
+            Month::Apr => "Apr",
+            Month::May => "May",
+            Month::Jun => "Jun",
+            Month::Jul => "Jul",
+            Month::Aug => "Aug",
+            Month::Sep => "Sep",
+            Month::Oct => "Oct",
+            Month::Nov => "Nov",
+            Month::Dec => "Dec",
+        }
+    }
+
+    pub fn file_code(&self) -> &'static str {
+        // monthly files use a single character for the month (eg. 46014a2024.txt.gz for October)
+        match self {
+            Month::Jan => "1",
+            Month::Feb => "2",
+            Month::Mar => "3",
+            Month::Apr => "4",
+            Month::May => "5",
+            Month::Jun => "6",
+            Month::Jul => "7",
+            Month::Aug => "8",
+            Month::Sep => "9",
+            Month::Oct => "a",
+            Month::Nov => "b",
+            Month::Dec => "c",
+        }
+    }
+
+    pub fn number(&self) -> u32 {
+        // January is 1
+        Month::ALL.iter().position(|m| m == self).map_or(1, |i| i as u32 + 1)
+    }
+
+    pub fn from_abbreviation(value: &str) -> Option<Month> {
+        Month::ALL.into_iter().find(|m| m.as_str() == value)
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -159,6 +404,146 @@ pub struct StationStdMetData {
     pub tide: Option<f32>,
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct StationDriftData {
+    pub station: String,
+    pub timestamp: NaiveDateTime,
+    pub lat: Option<f32>,
+    pub lon: Option<f32>,
+    pub wdir: Option<f32>,
+    pub wspd: Option<f32>,
+    pub gst: Option<f32>,
+    pub pres: Option<f32>,
+    pub ptdy: Option<f32>,
+    pub atmp: Option<f32>,
+    pub wtmp: Option<f32>,
+    pub dewp: Option<f32>,
+    pub wvht: Option<f32>,
+    pub dpd: Option<f32>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct GeoJsonLineString {
+    pub r#type: String,
+    pub coordinates: Vec<[f32; 2]>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct StationDriftTrackProperties {
+    pub station: String,
+    pub start: Option<NaiveDateTime>,
+    pub end: Option<NaiveDateTime>,
+    pub times: Vec<NaiveDateTime>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct StationDriftTrack {
+    pub r#type: String,
+    pub geometry: GeoJsonLineString,
+    pub properties: StationDriftTrackProperties,
+}
+
+impl StationDriftTrack {
+    pub fn new(station: &str, data: &[StationDriftData]) -> Self {
+        // This function builds a GeoJSON Feature with the drifter's path as a LineString, oldest position first.
+        // GeoJSON positions are [lon, lat], times[i] is when coordinates[i] was reported.
+        let mut positions: Vec<(NaiveDateTime, [f32; 2])> = data.iter().filter_map(|d| Some((d.timestamp, [d.lon?, d.lat?]))).collect();
+        positions.sort_by_key(|(t, _)| *t);
+        positions.dedup_by_key(|(t, _)| *t);
+
+        let times: Vec<NaiveDateTime> = positions.iter().map(|(t, _)| *t).collect();
+        let coordinates: Vec<[f32; 2]> = positions.into_iter().map(|(_, p)| p).collect();
+
+        StationDriftTrack { r#type: "Feature".to_string(), geometry: GeoJsonLineString { r#type: "LineString".to_string(), coordinates }, properties: StationDriftTrackProperties { station: station.to_uppercase(), start: times.first().copied(), end: times.last().copied(), times } }
+    }
+}
+
+/// The newest observation of a station from latest_obs.txt, with the position it was reported at.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct StationLatestObservation {
+    pub lat: Option<f32>,
+    pub lon: Option<f32>,
+    #[serde(flatten)]
+    pub observation: StationStdMetData,
+}
+
+/// The groups of measurements an observation can be filtered by (eg. `wave` for WVHT, DPD, APD and MWD).
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub enum StationObservationType {
+    Wind,
+    Wave,
+    Pressure,
+    Temperature,
+    Visibility,
+    Tide,
+}
+
+impl StationObservationType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StationObservationType::Wind => "wind",
+            StationObservationType::Wave => "wave",
+            StationObservationType::Pressure => "pressure",
+            StationObservationType::Temperature => "temperature",
+            StationObservationType::Visibility => "visibility",
+            StationObservationType::Tide => "tide",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<StationObservationType> {
+        match name.trim().to_lowercase().as_str() {
+            "wind" => Some(StationObservationType::Wind),
+            "wave" => Some(StationObservationType::Wave),
+            "pressure" => Some(StationObservationType::Pressure),
+            "temperature" => Some(StationObservationType::Temperature),
+            "visibility" => Some(StationObservationType::Visibility),
+            "tide" => Some(StationObservationType::Tide),
+            _ => None,
+        }
+    }
+}
+
+impl StationStdMetData {
+    /// Whether the observation has at least one measurement of the given type.
+    pub fn reports(&self, observation_type: StationObservationType) -> bool {
+        match observation_type {
+            StationObservationType::Wind => self.wdir.is_some() || self.wspd.is_some() || self.gst.is_some(),
+            StationObservationType::Wave => self.wvht.is_some() || self.dpd.is_some() || self.apd.is_some() || self.mwd.is_some(),
+            StationObservationType::Pressure => self.pres.is_some() || self.ptdy.is_some(),
+            StationObservationType::Temperature => self.atmp.is_some() || self.wtmp.is_some() || self.dewp.is_some(),
+            StationObservationType::Visibility => self.vis.is_some(),
+            StationObservationType::Tide => self.tide.is_some(),
+        }
+    }
+}
+
+/// A `min_lon,min_lat,max_lon,max_lat` bounding box, as used by GeoJSON.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub min_lon: f32,
+    pub min_lat: f32,
+    pub max_lon: f32,
+    pub max_lat: f32,
+}
+
+impl BoundingBox {
+    pub fn parse(value: &str) -> Option<BoundingBox> {
+        let bounds: Vec<f32> = value.split(',').map(|v| v.trim().parse().ok()).collect::<Option<Vec<f32>>>()?;
+
+        match bounds[..] {
+            [min_lon, min_lat, max_lon, max_lat] if min_lat <= max_lat => Some(BoundingBox { min_lon, min_lat, max_lon, max_lat }),
+            _ => None,
+        }
+    }
+
+    pub fn contains(&self, lat: f32, lon: f32) -> bool {
+        // a box with min_lon greater than max_lon crosses the antimeridian (eg. 170,-20,-170,20)
+        let within_lon: bool = if self.min_lon <= self.max_lon { self.min_lon <= lon && lon <= self.max_lon } else { self.min_lon <= lon || lon <= self.max_lon };
+
+        within_lon && self.min_lat <= lat && lat <= self.max_lat
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct StationContinuousWindsData {
     pub station: String,
@@ -185,29 +570,210 @@ pub struct StationSpectralWaveSummary {
     pub mwd: Option<f32>,
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct StationOceanCurrentBin {
+    pub bin: u32,
+    pub depth: Option<f32>,
+    pub dir: Option<f32>,
+    pub spd: Option<f32>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct StationOceanCurrentData {
+    pub station: String,
+    pub timestamp: NaiveDateTime,
+    pub bins: Vec<StationOceanCurrentBin>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct StationOceanographicData {
+    pub station: String,
+    pub timestamp: NaiveDateTime,
+    pub depth: Option<f32>,
+    pub otmp: Option<f32>,
+    pub cond: Option<f32>,
+    pub sal: Option<f32>,
+    pub o2pct: Option<f32>,
+    pub o2ppm: Option<f32>,
+    pub clcon: Option<f32>,
+    pub turb: Option<f32>,
+    pub ph: Option<f32>,
+    pub eh: Option<f32>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct StationWaterLevelData {
+    pub station: String,
+    pub timestamp: NaiveDateTime,
+    pub wlevel: Option<f32>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct StationSupplementalData {
+    pub station: String,
+    pub timestamp: NaiveDateTime,
+    pub pres: Option<f32>,
+    pub ptime: Option<NaiveDateTime>,
+    pub wspd: Option<f32>,
+    pub wdir: Option<f32>,
+    pub wtime: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct StationRainData {
+    pub timestamp: NaiveDateTime,
+    pub accum: Option<f32>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct StationRainUnits {
+    pub accum: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct StationRainSeries {
+    pub station: String,
+    pub units: StationRainUnits,
+    pub data: Vec<StationRainData>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct StationSolarRadiationData {
+    pub station: String,
+    pub timestamp: NaiveDateTime,
+    pub srad1: Option<f32>,
+    pub swrad: Option<f32>,
+    pub lwrad: Option<f32>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub enum DartMeasurementType {
+    FifteenMinute,
+    OneMinute,
+    FifteenSecond,
+}
+
+impl DartMeasurementType {
+    pub fn from_code(code: u8) -> Option<DartMeasurementType> {
+        // DART files mark every height with the sampling mode, tsunami events switch the tsunameter to 1-minute and 15-second reports
+        match code {
+            1 => Some(DartMeasurementType::FifteenMinute),
+            2 => Some(DartMeasurementType::OneMinute),
+            3 => Some(DartMeasurementType::FifteenSecond),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct StationDartData {
+    pub station: String,
+    pub timestamp: NaiveDateTime,
+    pub measurement_type: Option<DartMeasurementType>,
+    pub height: Option<f32>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct StationSpectralData {
+    pub station: String,
+    pub timestamp: NaiveDateTime,
+    pub data_type: StationDataType,
+    pub separation_frequency: Option<f32>,
+    pub frequency: Vec<f32>,
+    pub density: Vec<Option<f32>>,
+}
+
 pub fn check_null_string(value: &str) -> bool {
-    // This function may not be correct such that different attributes have different null values, but the null values are not shared between all of them.
-    match value {
-        "9" => true,
-        "9.0" => true,
-        "9.00" => true,
-        "9.000" => true,
-        "99" => true,
-        "99.0" => true,
-        "99.00" => true,
-        "99.000" => true,
-        "999" => true,
-        "999.0" => true,
-        "999.00" => true,
-        "999.000" => true,
-        "9999" => true,
-        "9999.0" => true,
-        "9999.00" => true,
-        "9999.000" => true,
-        "M" => true,
-        "MM" => true,
-        "MMM" => true,
-        "MMMM" => true,
-        _ => false,
+    // Only the textual markers are shared by every column, the numeric ones (eg. 99.0 for WSPD but 999 for WDIR) depend on the column and are passed to parse_nullable.
+    matches!(value, "M" | "MM" | "MMM" | "MMMM" | "N/A")
+}
+
+pub fn parse_nullable<T: FromStr>(value: &str, column: &str, line: usize, missing: Option<f64>) -> Result<Option<T>, NdbcError> {
+    // This function parses a single NDBC measurement, treating the missing-value markers as None instead of failing the whole file.
+    // The numeric marker is compared by value, as archives write it with a varying number of decimals (eg. 99.0 and 99.00).
+    if check_null_string(value) || (missing.is_some() && value.parse::<f64>().ok() == missing) {
+        return Ok(None);
+    }
+
+    value.parse().map(Some).map_err(|_| NdbcError::MalformedRow { line, column: column.to_string(), value: value.to_string() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_months_to_directories_and_file_codes() {
+        assert_eq!(Month::from_abbreviation("Oct"), Some(Month::Oct));
+        assert_eq!(Month::from_abbreviation("oct"), None);
+        assert_eq!(Month::ALL.map(|m| m.number()), [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]);
+        assert_eq!((Month::Sep.file_code(), Month::Oct.file_code(), Month::Dec.file_code()), ("9", "a", "c"));
+    }
+
+    fn deployment(start: &str, lat: f32, lng: f32) -> StationMetadataHistory {
+        StationMetadataHistory { start: NaiveDate::parse_from_str(start, "%Y-%m-%d").ok(), stop: None, lat: Some(lat), lng: Some(lng), elev: Some(0.0), met: None, hull: None, anemom_height: None }
+    }
+
+    #[test]
+    fn describes_inactive_stations_from_their_latest_deployment() {
+        let metadata: StationMetadata = StationMetadata { id: "46014".to_string(), name: Some("PT ARENA".to_string()), owner: Some("NDBC".to_string()), pgm: None, r#type: Some("buoy".to_string()), history: vec![deployment("2019-06-05", 39.23, -123.96), deployment("1981-09-01", 39.22, -123.97), deployment("2005-01-10", 39.24, -123.98)] };
+        let station: Station = Station::from_metadata(&metadata);
+
+        assert_eq!((station.id.as_str(), station.name.as_deref(), station.active), ("46014", Some("PT ARENA"), Some(false)));
+        assert_eq!((station.lat, station.lon), (Some(39.23), Some(-123.96)));
+
+        let without_history: Station = Station::from_metadata(&StationMetadata { history: Vec::new(), ..metadata });
+        assert_eq!((without_history.lat, without_history.lon, without_history.active), (None, None, Some(false)));
+    }
+
+    #[test]
+    fn merges_station_table_details_without_replacing_the_name_or_owner() {
+        let entry: StationTableEntry = StationTableEntry { id: "46014".to_string(), owner: Some("NDBC".to_string()), ttype: None, hull: Some("3D".to_string()), name: Some("PT ARENA - 19NM North of Point Arena".to_string()), payload: Some("SCOOP".to_string()), location: None, timezone: Some("P".to_string()), forecast: Some("PZZ570".to_string()), note: None };
+        let mut station: Station = Station { id: "46014".to_string(), name: Some("Pt Arena".to_string()), ..Default::default() };
+
+        station.merge_station_table(&entry);
+
+        assert_eq!((station.hull.as_deref(), station.payload.as_deref(), station.timezone.as_deref()), (Some("3D"), Some("SCOOP"), Some("America/Los_Angeles")));
+        assert_eq!((station.name.as_deref(), station.owner.as_deref()), (Some("Pt Arena"), Some("NDBC")));
+    }
+
+    #[test]
+    fn parses_bounding_boxes_across_the_antimeridian() {
+        let pacific: BoundingBox = BoundingBox::parse("170, -20, -170, 20").unwrap();
+
+        assert_eq!(pacific, BoundingBox { min_lon: 170.0, min_lat: -20.0, max_lon: -170.0, max_lat: 20.0 });
+        assert!(pacific.contains(0.0, 175.0) && pacific.contains(0.0, -175.0) && pacific.contains(20.0, 180.0));
+        assert!(!pacific.contains(0.0, 0.0) && !pacific.contains(0.0, 160.0) && !pacific.contains(25.0, 175.0));
+
+        let atlantic: BoundingBox = BoundingBox::parse("-80,20,-60,40").unwrap();
+        assert!(atlantic.contains(30.0, -70.0) && !atlantic.contains(30.0, 175.0));
+
+        assert_eq!((BoundingBox::parse("-80,40,-60,20"), BoundingBox::parse("-80,20,-60"), BoundingBox::parse("a,b,c,d")), (None, None, None));
+    }
+
+    fn drift(hour: u32, lat: Option<f32>, lon: Option<f32>) -> StationDriftData {
+        let timestamp: NaiveDateTime = NaiveDate::from_ymd_opt(2024, 10, 1).unwrap().and_hms_opt(hour, 0, 0).unwrap();
+
+        StationDriftData { station: "41001".to_string(), timestamp, lat, lon, wdir: None, wspd: None, gst: None, pres: None, ptdy: None, atmp: None, wtmp: None, dewp: None, wvht: None, dpd: None }
+    }
+
+    #[test]
+    fn builds_drift_tracks_oldest_position_first() {
+        let data: Vec<StationDriftData> = vec![drift(2, Some(34.5), Some(-72.5)), drift(0, Some(34.0), Some(-72.0)), drift(1, None, Some(-72.2)), drift(3, Some(34.9), None), drift(0, Some(34.1), Some(-72.1))];
+        let track: StationDriftTrack = StationDriftTrack::new("41001", &data);
+
+        // positions are [lon, lat], rows without a full position are left out and a repeated time keeps its first position
+        assert_eq!(track.geometry.coordinates, vec![[-72.0, 34.0], [-72.5, 34.5]]);
+        assert_eq!(track.properties.times, vec![data[1].timestamp, data[0].timestamp]);
+        assert_eq!((track.properties.start, track.properties.end), (Some(data[1].timestamp), Some(data[0].timestamp)));
+        assert_eq!((track.r#type.as_str(), track.geometry.r#type.as_str()), ("Feature", "LineString"));
+    }
+
+    #[test]
+    fn builds_empty_drift_tracks_without_positions() {
+        let track: StationDriftTrack = StationDriftTrack::new("41001", &[drift(0, None, None)]);
+
+        assert!(track.geometry.coordinates.is_empty());
+        assert_eq!((track.properties.start, track.properties.end), (None, None));
     }
 }
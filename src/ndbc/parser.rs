@@ -0,0 +1,698 @@
+use super::error::NdbcError;
+use super::ndbc_schema::{parse_nullable, DartMeasurementType, StationContinuousWindsData, StationDartData, StationDataType, StationDriftData, StationLatestObservation, StationOceanCurrentBin, StationOceanCurrentData, StationOceanographicData, StationRainData, StationRainSeries, StationRainUnits, StationSolarRadiationData, StationSpectralData, StationSpectralWaveSummary, StationStdMetData, StationSupplementalData, StationTableEntry, StationWaterLevelData};
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use log::{debug, warn};
+use std::str::FromStr;
+
+const TIMESTAMP_COLUMNS: [&str; 7] = ["YY", "MM", "DD", "hh", "mm", "ss", "hhmm"];
+
+const STDMET_COLUMNS: [&str; 16] = ["WDIR", "WSPD", "GST", "WVHT", "DPD", "APD", "MWD", "PRES", "ATMP", "WTMP", "DEWP", "VIS", "PTDY", "TIDE", "LAT", "LON"];
+const DRIFT_COLUMNS: [&str; 12] = ["LAT", "LON", "WDIR", "WSPD", "GST", "PRES", "PTDY", "ATMP", "WTMP", "DEWP", "WVHT", "DPD"];
+const LATEST_OBS_COLUMNS: [&str; 17] = ["STN", "LAT", "LON", "WDIR", "WSPD", "GST", "WVHT", "DPD", "APD", "MWD", "PRES", "PTDY", "ATMP", "WTMP", "DEWP", "VIS", "TIDE"];
+const CWIND_COLUMNS: [&str; 5] = ["WDIR", "WSPD", "GDR", "GST", "GTIME"];
+const OCEAN_COLUMNS: [&str; 10] = ["DEPTH", "OTMP", "COND", "SAL", "O2%", "O2PPM", "CLCON", "TURB", "PH", "EH"];
+const SUPL_COLUMNS: [&str; 5] = ["PRES", "PTIME", "WSPD", "WDIR", "WTIME"];
+const RAIN_COLUMNS: [&str; 1] = ["ACCUM"];
+const SRAD_COLUMNS: [&str; 3] = ["SRAD1", "SWRAD", "LWRAD"];
+const DART_COLUMNS: [&str; 2] = ["T", "HEIGHT"];
+const WLEVEL_COLUMNS: [&str; 2] = ["WLEVEL", "TIDE"];
+const SPEC_COLUMNS: [&str; 10] = ["WVHT", "SwH", "SwP", "WWH", "WWP", "SwD", "WWD", "STEEPNESS", "APD", "MWD"];
+
+// Spectral files mark a missing value with 999.00 (density) or 999.0 (directions and spreading), a direction of 9 or 99 degrees is real.
+const SPECTRAL_MISSING_VALUE: f64 = 999.0;
+
+fn missing_value(column: &str) -> Option<f64> {
+    // Historic archives mark a missing measurement with a column specific run of nines, realtime files use MM instead.
+    // Columns that are not listed only treat the textual markers as missing, so a real 9 or 99 is never dropped.
+    match column {
+        "WDIR" | "MWD" | "GDR" => Some(999.0),
+        "WSPD" | "GST" | "VIS" => Some(99.0),
+        "WVHT" | "DPD" | "APD" | "TIDE" | "WLEVEL" => Some(99.0),
+        "ATMP" | "WTMP" | "DEWP" | "OTMP" => Some(999.0),
+        "PRES" | "GTIME" | "PTIME" | "WTIME" => Some(9999.0),
+        "SRAD1" | "SWRAD" | "LWRAD" | "HEIGHT" => Some(9999.0),
+        _ => None,
+    }
+}
+
+fn canonical_column(name: &str) -> &str {
+    // NDBC has renamed a handful of columns over the years, older archives are mapped onto the current names
+    match name.trim_start_matches('#') {
+        "YYYY" => "YY",
+        "WD" | "DIR" => "WDIR",
+        "SPD" => "WSPD",
+        "GSP" => "GST",
+        "GMN" => "GTIME",
+        "BAR" => "PRES",
+        "DOMPD" => "DPD",
+        other => other,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NdbcTable<'a> {
+    pub columns: Vec<String>,
+    pub units: Option<Vec<String>>,
+    rows: Vec<(usize, Vec<&'a str>)>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct NdbcRow<'t, 'a> {
+    table: &'t NdbcTable<'a>,
+    line: usize,
+    values: &'t [&'a str],
+}
+
+impl<'a> NdbcTable<'a> {
+    pub fn parse(body: &'a str) -> Result<Self, NdbcError> {
+        // This function tokenizes an NDBC text file: a header line of column names, an optional `#` units line, then whitespace separated rows.
+        // Archives from before 2007 have no units line and no leading `#` on the header.
+        let mut columns: Option<Vec<String>> = None;
+        let mut units: Option<Vec<String>> = None;
+        let mut rows: Vec<(usize, Vec<&'a str>)> = Vec::new();
+
+        for (i, text) in body.lines().enumerate() {
+            let line: usize = i + 1;
+            let values: Vec<&'a str> = text.split_whitespace().collect();
+
+            if values.is_empty() {
+                continue;
+            }
+
+            match &columns {
+                None => columns = Some(values.iter().map(|v| canonical_column(v).to_string()).collect()),
+                Some(c) if text.starts_with('#') => {
+                    // only the line directly after the header carries units, any later `#` line is a comment
+                    // spectral archives only give units for the timestamp columns, so a shorter units line is accepted
+                    if units.is_none() && rows.is_empty() {
+                        if c.len() < values.len() {
+                            return Err(NdbcError::ColumnCount { line, expected: c.len(), found: values.len() });
+                        }
+                        units = Some(values.iter().map(|v| v.trim_start_matches('#').to_string()).collect());
+                    }
+                }
+                Some(c) if c.len() != values.len() => return Err(NdbcError::ColumnCount { line, expected: c.len(), found: values.len() }),
+                Some(_) => rows.push((line, values)),
+            }
+        }
+
+        let columns: Vec<String> = columns.unwrap_or_default();
+
+        for required in ["YY", "MM", "DD"] {
+            if !columns.iter().any(|c| c == required) {
+                return Err(NdbcError::MissingColumn { column: required.to_string() });
+            }
+        }
+        if !columns.iter().any(|c| c == "hh" || c == "hhmm") {
+            return Err(NdbcError::MissingColumn { column: "hh".to_string() });
+        }
+
+        Ok(NdbcTable { columns, units, rows })
+    }
+
+    pub fn check_columns(&self, known: &[&str]) {
+        // Columns are mapped by name, so an unexpected column can not shift the others, but it is still worth knowing about.
+        let unknown: Vec<&String> = self.columns.iter().filter(|c| !known.contains(&c.as_str()) && !TIMESTAMP_COLUMNS.contains(&c.as_str())).collect();
+        let missing: Vec<&&str> = known.iter().filter(|k| !self.columns.iter().any(|c| c == *k)).collect();
+
+        if !unknown.is_empty() {
+            warn!("Ignoring unknown columns: {:?}", unknown);
+        }
+        if !missing.is_empty() {
+            debug!("Columns not present in file: {:?}", missing);
+        }
+        debug!("Columns {:?} with units {:?}", self.columns, self.units);
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = NdbcRow<'_, 'a>> {
+        self.rows.iter().map(move |(line, values)| NdbcRow { table: self, line: *line, values })
+    }
+
+    pub fn unit(&self, column: &str) -> Option<&str> {
+        // units are only known for files with a units line
+        self.units.as_ref().and_then(|u| self.index(column).and_then(|i| u.get(i))).map(|u| u.as_str())
+    }
+
+    fn index(&self, column: &str) -> Option<usize> {
+        self.columns.iter().position(|c| c == column)
+    }
+}
+
+impl<'a> NdbcRow<'_, 'a> {
+    pub fn raw(&self, column: &str) -> Option<&'a str> {
+        self.table.index(column).map(|i| self.values[i])
+    }
+
+    pub fn get<T: FromStr>(&self, column: &str) -> Result<Option<T>, NdbcError> {
+        self.get_with_marker(column, missing_value(column))
+    }
+
+    pub fn get_with_marker<T: FromStr>(&self, column: &str, missing: Option<f64>) -> Result<Option<T>, NdbcError> {
+        // a column missing from the file is treated the same as a missing measurement, None only treats the textual markers (eg. MM) as missing
+        match self.raw(column) {
+            Some(value) => parse_nullable(value, column, self.line, missing),
+            None => Ok(None),
+        }
+    }
+
+    pub fn timestamp(&self) -> Result<NaiveDateTime, NdbcError> {
+        let field = |column: &str| -> Result<u32, NdbcError> {
+            let value: &str = self.raw(column).unwrap_or("0");
+
+            value.parse().map_err(|_| NdbcError::MalformedRow { line: self.line, column: column.to_string(), value: value.to_string() })
+        };
+
+        let mut year: u32 = field("YY")?;
+        if year < 100 {
+            // archives before 1999 use two digit years
+            year += 1900;
+        }
+
+        let (hour, minute) = match self.raw("hhmm") {
+            Some(_) => (field("hhmm")? / 100, field("hhmm")? % 100),
+            None => (field("hh")?, field("mm")?),
+        };
+
+        // only DART files report seconds
+        let second: u32 = field("ss")?;
+
+        NaiveDate::from_ymd_opt(year as i32, field("MM")?, field("DD")?).and_then(|d| d.and_hms_opt(hour, minute, second)).ok_or_else(|| NdbcError::MalformedRow { line: self.line, column: "timestamp".to_string(), value: self.values.join(" ") })
+    }
+
+    pub fn coordinate(&self, column: &str) -> Result<Option<f32>, NdbcError> {
+        // positions are only missing when they are MM, a latitude of 9 or 99 degrees is a real position and not a null marker
+        match self.raw(column) {
+            None | Some("MM") => Ok(None),
+            Some(value) => value.parse().map(Some).map_err(|_| NdbcError::MalformedRow { line: self.line, column: column.to_string(), value: value.to_string() }),
+        }
+    }
+
+    pub fn time_of_day(&self, column: &str) -> Result<Option<NaiveDateTime>, NdbcError> {
+        // This function reads an hhmm column (eg. the time of the peak wind within the hour) as a timestamp on the day of the row.
+        // A time after the row's timestamp belongs to the hour before midnight, so it is moved back a day.
+        let Some(hhmm) = self.get::<u32>(column)? else {
+            return Ok(None);
+        };
+
+        let time: NaiveTime = NaiveTime::from_hms_opt(hhmm / 100, hhmm % 100, 0).ok_or_else(|| NdbcError::MalformedRow { line: self.line, column: column.to_string(), value: hhmm.to_string() })?;
+        let timestamp: NaiveDateTime = self.timestamp()?;
+        let res: NaiveDateTime = timestamp.date().and_time(time);
+
+        if res > timestamp + Duration::hours(1) {
+            Ok(Some(res - Duration::days(1)))
+        } else {
+            Ok(Some(res))
+        }
+    }
+}
+
+pub fn parse_stdmet(station: &str, body: &str) -> Result<Vec<StationStdMetData>, NdbcError> {
+    // This function maps realtime, historic and drifting buoy stdmet files onto the same struct, columns a file does not have are left empty.
+    // Drifting buoys also report LAT and LON, use parse_drift to keep them.
+    let table = NdbcTable::parse(body)?;
+    table.check_columns(&STDMET_COLUMNS);
+
+    table.rows().map(|row| Ok(StationStdMetData { station: station.to_uppercase(), timestamp: row.timestamp()?, wdir: row.get("WDIR")?, wspd: row.get("WSPD")?, gst: row.get("GST")?, wvht: row.get("WVHT")?, dpd: row.get("DPD")?, apd: row.get("APD")?, mwd: row.get("MWD")?, pres: row.get("PRES")?, atmp: row.get("ATMP")?, wtmp: row.get("WTMP")?, dewp: row.get("DEWP")?, vis: row.get("VIS")?, ptdy: row.get("PTDY")?, tide: row.get("TIDE")? })).collect()
+}
+
+pub fn parse_drift(station: &str, body: &str) -> Result<Vec<StationDriftData>, NdbcError> {
+    // Drifting buoy files report a position with every observation and a combined hhmm time column.
+    let table = NdbcTable::parse(body)?;
+    table.check_columns(&DRIFT_COLUMNS);
+
+    table.rows().map(|row| Ok(StationDriftData { station: station.to_uppercase(), timestamp: row.timestamp()?, lat: row.coordinate("LAT")?, lon: row.coordinate("LON")?, wdir: row.get("WDIR")?, wspd: row.get("WSPD")?, gst: row.get("GST")?, pres: row.get("PRES")?, ptdy: row.get("PTDY")?, atmp: row.get("ATMP")?, wtmp: row.get("WTMP")?, dewp: row.get("DEWP")?, wvht: row.get("WVHT")?, dpd: row.get("DPD")? })).collect()
+}
+
+pub fn parse_latest_obs(body: &str) -> Result<Vec<StationLatestObservation>, NdbcError> {
+    // latest_obs.txt has one row per station, the station id is the STN column and every row carries the station's position.
+    let table = NdbcTable::parse(body)?;
+    table.check_columns(&LATEST_OBS_COLUMNS);
+
+    if !table.columns.iter().any(|c| c == "STN") {
+        return Err(NdbcError::MissingColumn { column: "STN".to_string() });
+    }
+
+    table
+        .rows()
+        .map(|row| {
+            let station: String = row.raw("STN").unwrap_or_default().to_uppercase();
+            let observation: StationStdMetData = StationStdMetData { station, timestamp: row.timestamp()?, wdir: row.get("WDIR")?, wspd: row.get("WSPD")?, gst: row.get("GST")?, wvht: row.get("WVHT")?, dpd: row.get("DPD")?, apd: row.get("APD")?, mwd: row.get("MWD")?, pres: row.get("PRES")?, atmp: row.get("ATMP")?, wtmp: row.get("WTMP")?, dewp: row.get("DEWP")?, vis: row.get("VIS")?, ptdy: row.get("PTDY")?, tide: row.get("TIDE")? };
+
+            Ok(StationLatestObservation { lat: row.coordinate("LAT")?, lon: row.coordinate("LON")?, observation })
+        })
+        .collect()
+}
+
+pub fn parse_cwind(station: &str, body: &str) -> Result<Vec<StationContinuousWindsData>, NdbcError> {
+    let table = NdbcTable::parse(body)?;
+    table.check_columns(&CWIND_COLUMNS);
+
+    table.rows().map(|row| Ok(StationContinuousWindsData { station: station.to_uppercase(), timestamp: row.timestamp()?, wdir: row.get("WDIR")?, wspd: row.get("WSPD")?, gdr: row.get("GDR")?, gst: row.get("GST")? })).collect()
+}
+
+pub fn parse_spec(station: &str, body: &str) -> Result<Vec<StationSpectralWaveSummary>, NdbcError> {
+    let table = NdbcTable::parse(body)?;
+    table.check_columns(&SPEC_COLUMNS);
+
+    table.rows().map(|row| Ok(StationSpectralWaveSummary { station: station.to_uppercase(), timestamp: row.timestamp()?, wvht: row.get("WVHT")?, swh: row.get("SwH")?, swp: row.get("SwP")?, wwh: row.get("WWH")?, wwp: row.get("WWP")?, swd: row.get("SwD")?, wwd: row.get("WWD")?, steep: row.get("STEEPNESS")?, apd: row.get("APD")?, mwd: row.get("MWD")? })).collect()
+}
+
+pub fn parse_ocean(station: &str, body: &str) -> Result<Vec<StationOceanographicData>, NdbcError> {
+    let table = NdbcTable::parse(body)?;
+    table.check_columns(&OCEAN_COLUMNS);
+
+    table.rows().map(|row| Ok(StationOceanographicData { station: station.to_uppercase(), timestamp: row.timestamp()?, depth: row.get("DEPTH")?, otmp: row.get("OTMP")?, cond: row.get("COND")?, sal: row.get("SAL")?, o2pct: row.get("O2%")?, o2ppm: row.get("O2PPM")?, clcon: row.get("CLCON")?, turb: row.get("TURB")?, ph: row.get("PH")?, eh: row.get("EH")? })).collect()
+}
+
+pub fn parse_supl(station: &str, body: &str) -> Result<Vec<StationSupplementalData>, NdbcError> {
+    // PRES is the lowest 1-minute pressure and WSPD/WDIR the highest 1-minute wind within the hour, PTIME and WTIME are when they were measured.
+    let table = NdbcTable::parse(body)?;
+    table.check_columns(&SUPL_COLUMNS);
+
+    table.rows().map(|row| Ok(StationSupplementalData { station: station.to_uppercase(), timestamp: row.timestamp()?, pres: row.get("PRES")?, ptime: row.time_of_day("PTIME")?, wspd: row.get("WSPD")?, wdir: row.get("WDIR")?, wtime: row.time_of_day("WTIME")? })).collect()
+}
+
+pub fn parse_rain(station: &str, body: &str) -> Result<StationRainSeries, NdbcError> {
+    // This function parses hourly precipitation, ACCUM is the accumulation over the hour.
+    // The unit is taken from the file when it has a units line, otherwise the millimeters NDBC documents are assumed.
+    let table = NdbcTable::parse(body)?;
+    table.check_columns(&RAIN_COLUMNS);
+
+    let units: StationRainUnits = StationRainUnits { accum: table.unit("ACCUM").unwrap_or("mm").to_string() };
+    let data: Vec<StationRainData> = table.rows().map(|row| Ok(StationRainData { timestamp: row.timestamp()?, accum: row.get("ACCUM")? })).collect::<Result<Vec<StationRainData>, NdbcError>>()?;
+
+    Ok(StationRainSeries { station: station.to_uppercase(), units, data })
+}
+
+pub fn parse_srad(station: &str, body: &str) -> Result<Vec<StationSolarRadiationData>, NdbcError> {
+    // SRAD1 and SWRAD are shortwave radiation from two different sensors, LWRAD is longwave radiation (all in w/m2).
+    let table = NdbcTable::parse(body)?;
+    table.check_columns(&SRAD_COLUMNS);
+
+    table.rows().map(|row| Ok(StationSolarRadiationData { station: station.to_uppercase(), timestamp: row.timestamp()?, srad1: row.get("SRAD1")?, swrad: row.get("SWRAD")?, lwrad: row.get("LWRAD")? })).collect()
+}
+
+pub fn parse_dart(station: &str, body: &str) -> Result<Vec<StationDartData>, NdbcError> {
+    // HEIGHT is the water column height in meters, T is the measurement type code (1 = 15-minute, 2 = 1-minute, 3 = 15-second).
+    let table = NdbcTable::parse(body)?;
+    table.check_columns(&DART_COLUMNS);
+
+    table
+        .rows()
+        .map(|row| {
+            let measurement_type: Option<DartMeasurementType> = match row.get::<u8>("T")? {
+                Some(code) => Some(DartMeasurementType::from_code(code).ok_or_else(|| NdbcError::MalformedRow { line: row.line, column: "T".to_string(), value: code.to_string() })?),
+                None => None,
+            };
+
+            Ok(StationDartData { station: station.to_uppercase(), timestamp: row.timestamp()?, measurement_type, height: row.get("HEIGHT")? })
+        })
+        .collect()
+}
+
+pub fn parse_wlevel(station: &str, body: &str) -> Result<Vec<StationWaterLevelData>, NdbcError> {
+    // Older water level archives report the level in a TIDE column instead of WLEVEL.
+    let table = NdbcTable::parse(body)?;
+    table.check_columns(&WLEVEL_COLUMNS);
+
+    let column: &str = if table.columns.iter().any(|c| c == "WLEVEL") { "WLEVEL" } else { "TIDE" };
+
+    table.rows().map(|row| Ok(StationWaterLevelData { station: station.to_uppercase(), timestamp: row.timestamp()?, wlevel: row.get(column)? })).collect()
+}
+
+pub fn parse_adcp(station: &str, body: &str) -> Result<Vec<StationOceanCurrentData>, NdbcError> {
+    // This function parses ocean current profiles, every depth bin has a DEPnn, DIRnn and SPDnn column (eg. DEP01 DIR01 SPD01).
+    // The number of bins depends on the instrument, bins without any measurement are left out.
+    // Only MM marks a missing value, a depth of 9 m or a direction of 99 degrees is a real measurement.
+    let table = NdbcTable::parse(body)?;
+
+    let bins: Vec<String> = table.columns.iter().filter_map(|c| c.strip_prefix("DEP")).filter(|n| n.chars().all(|c| c.is_ascii_digit())).map(|n| n.to_string()).collect();
+    let known: Vec<String> = bins.iter().flat_map(|n| ["DEP".to_string() + n, "DIR".to_string() + n, "SPD".to_string() + n]).collect();
+    table.check_columns(&known.iter().map(|c| c.as_str()).collect::<Vec<&str>>());
+
+    table
+        .rows()
+        .map(|row| {
+            let mut res: Vec<StationOceanCurrentBin> = Vec::new();
+
+            for n in &bins {
+                let bin = StationOceanCurrentBin { bin: n.parse().unwrap_or_default(), depth: row.get_with_marker(&("DEP".to_string() + n), None)?, dir: row.get_with_marker(&("DIR".to_string() + n), None)?, spd: row.get_with_marker(&("SPD".to_string() + n), None)? };
+
+                if bin.depth.is_some() || bin.dir.is_some() || bin.spd.is_some() {
+                    res.push(bin);
+                }
+            }
+
+            Ok(StationOceanCurrentData { station: station.to_uppercase(), timestamp: row.timestamp()?, bins: res })
+        })
+        .collect()
+}
+
+fn parse_spectral_timestamp(line: usize, values: &[&str]) -> Result<NaiveDateTime, NdbcError> {
+    let field = |i: usize, column: &str| -> Result<u32, NdbcError> {
+        let value: &str = values.get(i).copied().unwrap_or_default();
+
+        value.parse().map_err(|_| NdbcError::MalformedRow { line, column: column.to_string(), value: value.to_string() })
+    };
+
+    let (hour, minute) = (field(3, "hh")?, field(4, "mm")?);
+
+    NaiveDate::from_ymd_opt(field(0, "YY")? as i32, field(1, "MM")?, field(2, "DD")?).and_then(|d| d.and_hms_opt(hour, minute, 0)).ok_or_else(|| NdbcError::MalformedRow { line, column: "timestamp".to_string(), value: values.join(" ") })
+}
+
+pub fn parse_spectral(station: &str, data_type: StationDataType, body: &str) -> Result<Vec<StationSpectralData>, NdbcError> {
+    // This function parses the frequency binned spectral files (data_spec, swdir, swdir2, swr1, swr2).
+    // Rows are a timestamp followed by `value (frequency)` pairs, so the header does not line up with the values and is skipped.
+    // data_spec rows also carry the separation frequency between wind waves and swell in front of the pairs (9.999 when missing).
+    let has_separation_frequency: bool = data_type == StationDataType::SpectralWaveDensity;
+    let mut res: Vec<StationSpectralData> = Vec::new();
+
+    for (i, text) in body.lines().enumerate() {
+        let line: usize = i + 1;
+        let values: Vec<&str> = text.split_whitespace().collect();
+
+        if values.is_empty() || text.starts_with('#') {
+            continue;
+        }
+
+        let timestamp: NaiveDateTime = parse_spectral_timestamp(line, &values)?;
+
+        let (separation_frequency, pairs): (Option<f32>, &[&str]) = match (has_separation_frequency, values.get(5..)) {
+            (true, Some([sep, pairs @ ..])) => (if *sep == "9.999" { None } else { parse_nullable(sep, "Sep_Freq", line, None)? }, pairs),
+            (_, Some(pairs)) => (None, pairs),
+            (_, None) => return Err(NdbcError::ColumnCount { line, expected: 5, found: values.len() }),
+        };
+
+        if pairs.len() % 2 != 0 {
+            return Err(NdbcError::ColumnCount { line, expected: pairs.len() + 1, found: pairs.len() });
+        }
+
+        let mut frequency: Vec<f32> = Vec::with_capacity(pairs.len() / 2);
+        let mut density: Vec<Option<f32>> = Vec::with_capacity(pairs.len() / 2);
+
+        for pair in pairs.chunks(2) {
+            let freq: &str = pair[1].trim_start_matches('(').trim_end_matches(')');
+
+            frequency.push(freq.parse().map_err(|_| NdbcError::MalformedRow { line, column: "frequency".to_string(), value: pair[1].to_string() })?);
+            density.push(parse_nullable(pair[0], data_type.as_str(), line, Some(SPECTRAL_MISSING_VALUE))?);
+        }
+
+        res.push(StationSpectralData { station: station.to_uppercase(), timestamp, data_type: data_type.clone(), separation_frequency, frequency, density });
+    }
+
+    Ok(res)
+}
+
+pub fn parse_historic_spectral(station: &str, data_type: StationDataType, body: &str) -> Result<Vec<StationSpectralData>, NdbcError> {
+    // Historic spectral archives name every column after its frequency (eg. .0200 .0325), so the frequencies are read from the header.
+    // Unlike the realtime files the values line up with the header, separation frequency is only kept when the archive has a Sep_Freq column.
+    let table = NdbcTable::parse(body)?;
+
+    let frequency_columns: Vec<&String> = table.columns.iter().filter(|c| !TIMESTAMP_COLUMNS.contains(&c.as_str()) && c.as_str() != "Sep_Freq").collect();
+    let frequency: Vec<f32> = frequency_columns.iter().map(|c| c.parse().map_err(|_| NdbcError::MalformedRow { line: 1, column: "frequency".to_string(), value: c.to_string() })).collect::<Result<Vec<f32>, NdbcError>>()?;
+
+    let mut known: Vec<&str> = frequency_columns.iter().map(|c| c.as_str()).collect();
+    known.push("Sep_Freq");
+    table.check_columns(&known);
+
+    table
+        .rows()
+        .map(|row| {
+            let separation_frequency: Option<f32> = match row.raw("Sep_Freq") {
+                Some("9.999") | None => None,
+                Some(_) => row.get("Sep_Freq")?,
+            };
+            let density: Vec<Option<f32>> = frequency_columns.iter().map(|c| row.get_with_marker(c, Some(SPECTRAL_MISSING_VALUE))).collect::<Result<Vec<Option<f32>>, NdbcError>>()?;
+
+            Ok(StationSpectralData { station: station.to_uppercase(), timestamp: row.timestamp()?, data_type: data_type.clone(), separation_frequency, frequency: frequency.clone(), density })
+        })
+        .collect()
+}
+
+pub fn parse_station_table(body: &str) -> Result<Vec<StationTableEntry>, NdbcError> {
+    // This function parses data/stations/station_table.txt, a pipe delimited table with `#` header lines:
+    // STATION_ID | OWNER | TTYPE | HULL | NAME | PAYLOAD | LOCATION | TIMEZONE | FORECAST | NOTE
+    // A malformed row only loses that station's details, so it is skipped instead of failing the whole table.
+    let res: Vec<StationTableEntry> = body
+        .lines()
+        .enumerate()
+        .filter(|(_, text)| !text.trim().is_empty() && !text.starts_with('#'))
+        .filter_map(|(i, text)| {
+            let fields: Vec<&str> = text.split('|').map(|f| f.trim()).collect();
+
+            if fields.len() < 10 {
+                debug!("Skipping line {} of station_table.txt with {} of 10 fields", i + 1, fields.len());
+                return None;
+            }
+
+            // the degree signs in the location are html encoded, and a note may itself contain a pipe
+            let field = |value: &str| -> Option<String> { Some(value).filter(|v| !v.is_empty()).map(|v| v.replace("&#176;", "°")) };
+
+            Some(StationTableEntry { id: fields[0].to_uppercase(), owner: field(fields[1]), ttype: field(fields[2]), hull: field(fields[3]), name: field(fields[4]), payload: field(fields[5]), location: field(fields[6]), timezone: field(fields[7]), forecast: field(fields[8]), note: field(&fields[9..].join("|")) })
+        })
+        .collect();
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ndbc::ndbc_schema::StationObservationType;
+
+    fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap().and_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn parses_two_digit_years_without_minutes() {
+        // archives before 1999: two digit year, no minute column, WD and BAR instead of WDIR and PRES
+        let body = "YY MM DD hh  WD  WSPD GST  WVHT  DPD   APD  MWD  BAR    ATMP  WTMP  DEWP  VIS\n88 01 01 00 270  5.2  6.8  1.20  8.00  5.10 999 1016.5  12.3  13.1 999.0 99.0\n";
+        let res = parse_stdmet("46014", body).unwrap();
+
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].timestamp, at(1988, 1, 1, 0, 0));
+        assert_eq!(res[0].wdir, Some(270.0));
+        assert_eq!(res[0].pres, Some(1016.5));
+        assert_eq!((res[0].mwd, res[0].dewp, res[0].vis), (None, None, None));
+    }
+
+    #[test]
+    fn parses_four_digit_years_without_units() {
+        let body = "YYYY MM DD hh  WD  WSPD GST  WVHT  DPD   APD  MWD  BAR    ATMP  WTMP  DEWP  VIS  TIDE\n2003 07 15 12 180  3.1  4.0  0.80  9.09  6.20 200 1012.3  18.4  17.9  15.0 99.0 99.00\n";
+        let table = NdbcTable::parse(body).unwrap();
+        let res = parse_stdmet("46014", body).unwrap();
+
+        assert!(table.units.is_none());
+        assert_eq!(res[0].timestamp, at(2003, 7, 15, 12, 0));
+        assert_eq!(res[0].wvht, Some(0.8));
+        assert_eq!((res[0].vis, res[0].tide), (None, None));
+    }
+
+    #[test]
+    fn parses_commented_header_with_units() {
+        let body = "#YY  MM DD hh mm WDIR WSPD GST  WVHT   DPD   APD MWD   PRES  ATMP  WTMP  DEWP  VIS  TIDE\n#yr  mo dy hr mn degT m/s  m/s     m   sec   sec degT   hPa  degC  degC  degC  mi    ft\n2019 03 04 05 50 270  5.0  6.0  1.50    12  7.10 280 1020.1   8.0  11.2   5.5 99.0 99.00\n";
+        let table = NdbcTable::parse(body).unwrap();
+        let res = parse_stdmet("46014", body).unwrap();
+
+        assert_eq!(table.unit("WSPD"), Some("m/s"));
+        assert_eq!(res[0].timestamp, at(2019, 3, 4, 5, 50));
+        assert_eq!(res[0].pres, Some(1020.1));
+    }
+
+    #[test]
+    fn keeps_real_values_that_look_like_missing_markers() {
+        // 99 degrees, 9 m/s, 9 seconds and 9 degrees C are real measurements, only the column's own marker is missing
+        let body = "#YY  MM DD hh mm WDIR WSPD GST  WVHT   DPD   APD MWD   PRES  ATMP  WTMP  DEWP  VIS  TIDE\n#yr  mo dy hr mn degT m/s  m/s     m   sec   sec degT   hPa  degC  degC  degC  mi    ft\n2019 03 04 05 50  99  9.0 99.0 99.00     9  7.10 999 9999.0  9.0 999.0   MM 99.0 99.00\n";
+        let res = parse_stdmet("46014", body).unwrap();
+
+        assert_eq!((res[0].wdir, res[0].wspd, res[0].dpd, res[0].atmp), (Some(99.0), Some(9.0), Some(9.0), Some(9.0)));
+        assert_eq!((res[0].gst, res[0].wvht, res[0].mwd, res[0].pres, res[0].wtmp, res[0].dewp), (None, None, None, None, None, None));
+    }
+
+    #[test]
+    fn parses_combined_hhmm_column() {
+        let body = "#YY  MM DD hhmm     LAT      LON  WDIR WSPD  GST   PRES PTDY  ATMP  WTMP  DEWP  WVHT  DPD\n#yr  mo dy hrmn     deg      deg  degT  m/s  m/s    hPa  hPa  degC  degC  degC     m  sec\n2024 10 01 2330  41.234 -150.123   MM   MM   MM 1012.3   MM    MM  18.2    MM    MM   MM\n";
+        let res = parse_drift("41001", body).unwrap();
+
+        assert_eq!(res[0].timestamp, at(2024, 10, 1, 23, 30));
+        assert_eq!((res[0].lat, res[0].lon), (Some(41.234), Some(-150.123)));
+        assert_eq!((res[0].pres, res[0].wtmp, res[0].wdir), (Some(1012.3), Some(18.2), None));
+    }
+
+    #[test]
+    fn maps_renamed_cwind_columns() {
+        let body = "YYYY MM DD hh mm DIR  SPD GDR  GSP  GMN\n2004 01 01 00 00 300  6.1 310  8.2 1250\n";
+        let res = parse_cwind("46014", body).unwrap();
+
+        assert_eq!((res[0].wdir, res[0].wspd, res[0].gdr, res[0].gst), (Some(300.0), Some(6.1), Some(310.0), Some(8.2)));
+    }
+
+    #[test]
+    fn keeps_adcp_bins_with_values_that_look_like_missing_markers() {
+        let body = "#YY  MM DD hh mm DEP01 DIR01 SPD01 DEP02 DIR02 SPD02\n#yr  mo dy hr mn     m  degT  cm/s     m  degT  cm/s\n2024 10 01 00 00     9    99     9    MM    MM    MM\n";
+        let res = parse_adcp("42887", body).unwrap();
+
+        assert_eq!(res[0].bins.len(), 1);
+        assert_eq!((res[0].bins[0].bin, res[0].bins[0].depth, res[0].bins[0].dir, res[0].bins[0].spd), (1, Some(9.0), Some(99.0), Some(9.0)));
+    }
+
+    #[test]
+    fn parses_the_separation_frequency_of_spectral_densities() {
+        let body = "#YY  MM DD hh mm\n#yr  mo dy hr mn\n2024 10 01 00 00 0.090 0.000 (0.033) 1.250 (0.038) 999.00 (0.043)\n2024 10 01 01 00 9.999 0.010 (0.033) 2.500 (0.038) 0.000 (0.043)\n";
+        let res = parse_spectral("46014", StationDataType::SpectralWaveDensity, body).unwrap();
+
+        assert_eq!((res[0].separation_frequency, res[1].separation_frequency), (Some(0.09), None));
+        assert_eq!(res[0].frequency, vec![0.033, 0.038, 0.043]);
+        assert_eq!(res[0].density, vec![Some(0.0), Some(1.25), None]);
+        assert_eq!(res[1].density, vec![Some(0.01), Some(2.5), Some(0.0)]);
+    }
+
+    #[test]
+    fn keeps_spectral_directions_that_look_like_missing_markers() {
+        let body = "#YY  MM DD hh mm\n#yr  mo dy hr mn\n2024 10 01 00 00 99.0 (0.033) 254.0 (0.038) 999.0 (0.043) MM (0.048)\n";
+        let res = parse_spectral("46014", StationDataType::SpectralWaveA1Density, body).unwrap();
+
+        assert_eq!(res[0].frequency, vec![0.033, 0.038, 0.043, 0.048]);
+        assert_eq!(res[0].density, vec![Some(99.0), Some(254.0), None, None]);
+    }
+
+    #[test]
+    fn parses_the_separation_frequency_of_historic_spectral_densities() {
+        let body = "#YY  MM DD hh mm Sep_Freq  .0200  .0325  .0375\n#yr  mo dy hr mn\n2019 01 01 00 00    0.090   0.00   1.25 999.00\n2019 01 01 01 00    9.999   0.01   2.50   0.00\n";
+        let res = parse_historic_spectral("46014", StationDataType::SpectralWaveDensity, body).unwrap();
+
+        assert_eq!((res[0].separation_frequency, res[1].separation_frequency), (Some(0.09), None));
+        assert_eq!(res[0].frequency, vec![0.02, 0.0325, 0.0375]);
+        assert_eq!(res[0].density, vec![Some(0.0), Some(1.25), None]);
+
+        let without_column = parse_historic_spectral("46014", StationDataType::SpectralWaveDensity, "#YY  MM DD hh mm  .0200\n#yr  mo dy hr mn\n2019 01 01 00 00   0.00\n").unwrap();
+        assert_eq!((without_column[0].separation_frequency, without_column[0].frequency.clone()), (None, vec![0.02]));
+    }
+
+    #[test]
+    fn keeps_historic_spectral_directions_that_look_like_missing_markers() {
+        let body = "#YY  MM DD hh mm  .0200  .0325  .0375  .0425\n#yr  mo dy hr mn\n2019 01 01 00 00   99.0  254.0    9.0  999.0\n";
+        let res = parse_historic_spectral("46014", StationDataType::SpectralWaveA1Density, body).unwrap();
+
+        assert_eq!(res[0].frequency, vec![0.02, 0.0325, 0.0375, 0.0425]);
+        assert_eq!(res[0].density, vec![Some(99.0), Some(254.0), Some(9.0), None]);
+    }
+
+    #[test]
+    fn parses_oceanographic_measurements() {
+        let body = "#YY  MM DD hh mm   DEPTH  OTMP   COND   SAL   O2% O2PPM  CLCON  TURB    PH    EH\n#yr  mo dy hr mn       m  degC  mS/cm   psu     %   ppm   ug/l   FTU     -    mv\n2024 10 01 00 00     1.0 14.62  42.81 33.55    MM    MM   0.52    MM  8.02    MM\n2024 10 01 01 00     1.0 999.0     MM    MM    MM    MM     MM    MM    MM    MM\n";
+        let res = parse_ocean("46014", body).unwrap();
+
+        assert_eq!((res[0].depth, res[0].otmp, res[0].sal, res[0].clcon, res[0].ph), (Some(1.0), Some(14.62), Some(33.55), Some(0.52), Some(8.02)));
+        assert_eq!((res[0].o2pct, res[0].turb, res[0].eh), (None, None, None));
+        assert_eq!(res[1].otmp, None);
+    }
+
+    #[test]
+    fn moves_peak_times_before_midnight_back_a_day() {
+        let table = NdbcTable::parse("#YY  MM DD hh mm GTIME\n#yr  mo dy hr mn hrmn\n2024 10 02 00 00  2350\n2024 10 02 12 00  1150\n2024 10 02 12 00  9999\n").unwrap();
+        let res: Vec<Option<NaiveDateTime>> = table.rows().map(|row| row.time_of_day("GTIME").unwrap()).collect();
+
+        assert_eq!(res, vec![Some(at(2024, 10, 1, 23, 50)), Some(at(2024, 10, 2, 11, 50)), None]);
+    }
+
+    #[test]
+    fn parses_supplemental_peak_times() {
+        let body = "#YY  MM DD hh mm   PRES PTIME WSPD WDIR WTIME\n#yr  mo dy hr mn    hPa  hhmm  m/s degT  hhmm\n2024 10 02 00 00 1009.8  2350 14.2  250  0005\n";
+        let res = parse_supl("46014", body).unwrap();
+
+        assert_eq!((res[0].pres, res[0].wspd, res[0].wdir), (Some(1009.8), Some(14.2), Some(250.0)));
+        assert_eq!((res[0].ptime, res[0].wtime), (Some(at(2024, 10, 1, 23, 50)), Some(at(2024, 10, 2, 0, 5))));
+    }
+
+    #[test]
+    fn rejects_invalid_peak_times() {
+        let body = "#YY  MM DD hh mm   PRES PTIME WSPD WDIR WTIME\n#yr  mo dy hr mn    hPa  hhmm  m/s degT  hhmm\n2024 10 02 00 00 1009.8  2350 14.2  250  2375\n";
+
+        assert!(matches!(parse_supl("46014", body), Err(NdbcError::MalformedRow { line: 3, column, value }) if column == "WTIME" && value == "2375"));
+    }
+
+    #[test]
+    fn parses_hourly_rain_with_its_unit() {
+        let with_units = parse_rain("46014", "#YY  MM DD hh mm ACCUM\n#yr  mo dy hr mn    in\n2024 10 01 00 00  0.12\n2024 10 01 01 00    MM\n").unwrap();
+        let without_units = parse_rain("46014", "YYYY MM DD hh mm ACCUM\n2024 10 01 00 00  3.0\n").unwrap();
+
+        assert_eq!((with_units.units.accum.as_str(), with_units.data[0].accum, with_units.data[1].accum), ("in", Some(0.12), None));
+        assert_eq!((without_units.units.accum.as_str(), without_units.data[0].accum), ("mm", Some(3.0)));
+    }
+
+    #[test]
+    fn parses_solar_radiation() {
+        let realtime = parse_srad("46014", "#YY  MM DD hh mm  SRAD1  SWRAD  LWRAD\n#yr  mo dy hr mn   w/m2   w/m2   w/m2\n2024 10 01 20 00  612.3     MM  351.0\n").unwrap();
+        let historic = parse_srad("46014", "#YY  MM DD hh mm  SRAD1  SWRAD  LWRAD\n#yr  mo dy hr mn   w/m2   w/m2   w/m2\n2019 01 01 00 00    9.0 9999.0 9999.0\n").unwrap();
+
+        assert_eq!((realtime[0].srad1, realtime[0].swrad, realtime[0].lwrad), (Some(612.3), None, Some(351.0)));
+        assert_eq!((historic[0].srad1, historic[0].swrad, historic[0].lwrad), (Some(9.0), None, None));
+    }
+
+    #[test]
+    fn parses_dart_heights_and_measurement_types() {
+        let body = "#YY  MM DD hh mm ss T   HEIGHT\n#yr  mo dy hr mn  s -        m\n2024 10 01 00 00 00 1 5801.450\n2024 10 01 00 01 00 2 5801.447\n2024 10 01 00 01 15 3 9999.000\n";
+        let res = parse_dart("21413", body).unwrap();
+        let types: Vec<Option<DartMeasurementType>> = res.iter().map(|d| d.measurement_type).collect();
+
+        assert_eq!(types, vec![Some(DartMeasurementType::FifteenMinute), Some(DartMeasurementType::OneMinute), Some(DartMeasurementType::FifteenSecond)]);
+        assert_eq!((res[0].height, res[2].height), (Some(5801.45), None));
+        assert_eq!(res[2].timestamp, NaiveDate::from_ymd_opt(2024, 10, 1).unwrap().and_hms_opt(0, 1, 15).unwrap());
+    }
+
+    #[test]
+    fn rejects_unknown_dart_measurement_types() {
+        let body = "#YY  MM DD hh mm ss T   HEIGHT\n#yr  mo dy hr mn  s -        m\n2024 10 01 00 00 00 7 5801.450\n";
+
+        assert!(matches!(parse_dart("21413", body), Err(NdbcError::MalformedRow { column, value, .. }) if column == "T" && value == "7"));
+    }
+
+    #[test]
+    fn reads_water_levels_from_the_tide_column_of_older_archives() {
+        let current = parse_wlevel("8771341", "#YY  MM DD hh mm WLEVEL\n#yr  mo dy hr mn     ft\n2019 01 01 00 00   1.25\n2019 01 01 00 06  99.00\n").unwrap();
+        let older = parse_wlevel("8771341", "YYYY MM DD hh mm  TIDE\n2005 01 01 00 00  1.10\n").unwrap();
+
+        assert_eq!(current.iter().map(|w| w.wlevel).collect::<Vec<Option<f32>>>(), vec![Some(1.25), None]);
+        assert_eq!((older[0].wlevel, older[0].timestamp), (Some(1.1), at(2005, 1, 1, 0, 0)));
+    }
+
+    #[test]
+    fn parses_the_station_table_and_skips_malformed_rows() {
+        let body = "# STATION_ID | OWNER | TTYPE | HULL | NAME | PAYLOAD | LOCATION | TIMEZONE | FORECAST | NOTE\n#\n46014|NDBC|Weather Buoy|3D|PT ARENA|SCOOP|39.225 N 123.980 W (39&#176;13'30\" N 123&#176;58'48\" W)|P|PZZ570|a note | with a pipe\n41001|NDBC|Weather Buoy|6N\nsauf1|NOS|Fixed|||||||\n";
+        let res = parse_station_table(body).unwrap();
+
+        assert_eq!(res.iter().map(|e| e.id.as_str()).collect::<Vec<&str>>(), vec!["46014", "SAUF1"]);
+        assert_eq!((res[0].hull.as_deref(), res[0].payload.as_deref(), res[0].note.as_deref()), (Some("3D"), Some("SCOOP"), Some("a note|with a pipe")));
+        assert_eq!(res[0].location.as_deref(), Some("39.225 N 123.980 W (39°13'30\" N 123°58'48\" W)"));
+        assert_eq!((res[0].time_zone(), res[1].hull.as_deref(), res[1].time_zone()), (Some("America/Los_Angeles"), None, None));
+    }
+
+    #[test]
+    fn parses_latest_observations_with_their_positions() {
+        let body = "#STN       LAT      LON  YYYY MM DD hh mm WDIR WSPD   GST WVHT  DPD APD MWD   PRES  PTDY  ATMP  WTMP  DEWP  VIS   TIDE\n#text      deg      deg   yr mo day hr mn degT  m/s   m/s   m   sec sec degT   hPa   hPa  degC  degC  degC  nmi     ft\n46014   39.225 -123.980 2024 10 01 00 00  290  7.0   9.0  1.9   10 7.2 300 1015.1 +0.4  13.1  13.5  11.9   MM     MM\nsauf1       MM       MM 2024 10 01 00 06   MM   MM    MM   MM   MM  MM  MM     MM   MM    MM    MM    MM   MM   1.25\n";
+        let res = parse_latest_obs(body).unwrap();
+
+        assert_eq!((res[0].observation.station.as_str(), res[0].lat, res[0].lon), ("46014", Some(39.225), Some(-123.98)));
+        assert_eq!((res[0].observation.timestamp, res[0].observation.wdir, res[0].observation.ptdy, res[0].observation.vis), (at(2024, 10, 1, 0, 0), Some(290.0), Some(0.4), None));
+        assert!(res[0].observation.reports(StationObservationType::Wave) && !res[0].observation.reports(StationObservationType::Tide));
+        assert_eq!((res[1].observation.station.as_str(), res[1].lat, res[1].observation.tide), ("SAUF1", None, Some(1.25)));
+        assert!(!res[1].observation.reports(StationObservationType::Wind) && res[1].observation.reports(StationObservationType::Tide));
+
+        assert!(matches!(parse_latest_obs("#YY  MM DD hh mm WDIR\n#yr  mo dy hr mn degT\n2024 10 01 00 00  290\n"), Err(NdbcError::MissingColumn { column }) if column == "STN"));
+    }
+
+    #[test]
+    fn rejects_rows_with_the_wrong_column_count() {
+        let body = "#YY  MM DD hh mm WDIR\n#yr  mo dy hr mn degT\n2024 10 01 00 00\n";
+
+        assert!(matches!(NdbcTable::parse(body), Err(NdbcError::ColumnCount { line: 3, expected: 6, found: 5 })));
+    }
+
+    #[test]
+    fn rejects_headers_without_a_date() {
+        assert!(matches!(NdbcTable::parse("#YY  MM hh mm WDIR\n2024 10 00 00 270\n"), Err(NdbcError::MissingColumn { column }) if column == "DD"));
+        assert!(matches!(NdbcTable::parse("#YY  MM DD WDIR\n2024 10 01 270\n"), Err(NdbcError::MissingColumn { column }) if column == "hh"));
+    }
+}
@@ -0,0 +1,155 @@
+use super::client::{Fetch, NdbcClient};
+use super::error::NdbcError;
+use super::ndbc_schema::{StationContinuousWindsData, StationDataType, StationHistoricFile, StationRealtimeFile, StationStdMetData};
+use super::parser::{parse_cwind, parse_stdmet};
+use chrono::{Duration, NaiveDateTime};
+use futures::{stream, StreamExt, TryStreamExt};
+use log::debug;
+use std::collections::BTreeMap;
+
+/// The longest range a single time range query may cover, every year in the range is another archive to download.
+pub const MAX_RANGE_DAYS: i64 = 366;
+
+impl NdbcClient {
+    /// Returns the standard meteorological data for a station between start and end (inclusive, at most [`MAX_RANGE_DAYS`] apart), from whichever yearly, monthly and realtime files cover it.
+    pub async fn get_station_stdmet_data(&self, station: &str, start: NaiveDateTime, end: NaiveDateTime) -> Result<Vec<StationStdMetData>, NdbcError> {
+        // This function spares the caller from knowing which archive holds which part of the range.
+        debug!("get_station_stdmet_data");
+
+        self.get_station_range_data(station, StationDataType::StandardMeteorological, start, end, parse_stdmet, |d: &StationStdMetData| d.timestamp).await
+    }
+
+    /// Returns the continuous winds data for a station between start and end (inclusive, at most [`MAX_RANGE_DAYS`] apart), from whichever yearly, monthly and realtime files cover it.
+    pub async fn get_station_cwind_data(&self, station: &str, start: NaiveDateTime, end: NaiveDateTime) -> Result<Vec<StationContinuousWindsData>, NdbcError> {
+        // This function spares the caller from knowing which archive holds which part of the range.
+        debug!("get_station_cwind_data");
+
+        self.get_station_range_data(station, StationDataType::ContinuousWinds, start, end, parse_cwind, |d: &StationContinuousWindsData| d.timestamp).await
+    }
+
+    async fn get_station_range_data<T>(&self, station: &str, data_type: StationDataType, start: NaiveDateTime, end: NaiveDateTime, parse: fn(&str, &str) -> Result<Vec<T>, NdbcError>, timestamp: fn(&T) -> NaiveDateTime) -> Result<Vec<T>, NdbcError>
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        // This function picks the archives that overlap the range, downloads them concurrently and merges them in time order.
+        // The yearly and monthly archives are quality controlled, so the realtime file only fills in what comes after the last of them.
+        check_range(start, end)?;
+
+        let station: String = station.to_uppercase();
+
+        let (historic_files, realtime_files) = futures::try_join!(self.get_station_available_downloads(&station, data_type.clone()), self.get_realtime_files(data_type))?;
+
+        let historic_files: Vec<StationHistoricFile> = historic_files
+            .into_iter()
+            .filter(|f: &StationHistoricFile| match f.period() {
+                Some((from, to)) => from <= end && start < to,
+                None => {
+                    debug!("Skipping archive with an unknown period: {}", &f.filename);
+                    false
+                }
+            })
+            .collect();
+
+        // realtime records within an archive's period are left out, even when quality control removed that timestamp from the archive
+        let realtime_from: Option<NaiveDateTime> = historic_files.iter().filter_map(|f: &StationHistoricFile| f.period()).map(|(_, to)| to).max();
+        let realtime_file: Option<StationRealtimeFile> = realtime_files.into_iter().find(|f: &StationRealtimeFile| f.station == station && f.period().0 <= end && realtime_from.unwrap_or(start).max(start) <= f.period().1);
+        debug!("Sources for {station}: {:?} and realtime {:?} from {:?}", historic_files.iter().map(|f: &StationHistoricFile| &f.filename).collect::<Vec<&String>>(), realtime_file.as_ref().map(|f: &StationRealtimeFile| &f.filename), realtime_from);
+
+        let historic = stream::iter(historic_files)
+            .map(|file: StationHistoricFile| {
+                let station: String = station.clone();
+
+                async move { self.get_historic_file(&file, move |body| parse(&station, body)).await }
+            })
+            .buffered(self.crawl_concurrency())
+            .try_collect::<Vec<Vec<T>>>();
+
+        let realtime = async {
+            match &realtime_file {
+                Some(file) => {
+                    let url: String = self.url("data/realtime2/") + &file.filename;
+                    debug!("url {}", &url);
+
+                    let station: String = station.clone();
+                    self.get_parsed(&url, Fetch::Text, move |body| parse(&station, body)).await
+                }
+                None => Ok(Vec::new()),
+            }
+        };
+
+        let (historic, realtime) = futures::try_join!(historic, realtime)?;
+
+        Ok(merge_by_timestamp(historic.concat(), realtime, realtime_from, timestamp, start, end))
+    }
+}
+
+fn check_range(start: NaiveDateTime, end: NaiveDateTime) -> Result<(), NdbcError> {
+    // a range is rejected rather than truncated, so a caller never mistakes a partial result for the whole range
+    if start > end {
+        return Err(NdbcError::InvalidQuery { parameter: "start".to_string(), value: start.to_string() });
+    }
+    // a range that is too long is reported on its end, with the span so the caller knows by how much
+    if end - start > Duration::days(MAX_RANGE_DAYS) {
+        return Err(NdbcError::InvalidQuery { parameter: "end".to_string(), value: format!("{end} ({} days after start, at most {MAX_RANGE_DAYS})", (end - start).num_days()) });
+    }
+
+    Ok(())
+}
+
+fn merge_by_timestamp<T>(quality_controlled: Vec<T>, realtime: Vec<T>, realtime_from: Option<NaiveDateTime>, timestamp: fn(&T) -> NaiveDateTime, start: NaiveDateTime, end: NaiveDateTime) -> Vec<T> {
+    // quality controlled records are inserted first, a yearly and a monthly archive may both have a record, in which case the first is kept
+    // realtime records are only used from realtime_from on, the end of the last archive, and never replace an archived record
+    let mut merged: BTreeMap<NaiveDateTime, T> = BTreeMap::new();
+    let realtime = realtime.into_iter().filter(|r: &T| realtime_from.is_none_or(|from: NaiveDateTime| timestamp(r) >= from));
+
+    for record in quality_controlled.into_iter().chain(realtime) {
+        let ts: NaiveDateTime = timestamp(&record);
+
+        if start <= ts && ts <= end {
+            merged.entry(ts).or_insert(record);
+        }
+    }
+
+    merged.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(month: u32, day: u32, hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, month, day).unwrap().and_hms_opt(hour, 0, 0).unwrap()
+    }
+
+    fn timestamp(record: &(NaiveDateTime, &'static str)) -> NaiveDateTime {
+        record.0
+    }
+
+    #[test]
+    fn keeps_archived_records_over_realtime_ones() {
+        let archived = vec![(at(1, 31, 22), "qc"), (at(1, 31, 23), "qc")];
+        let realtime = vec![(at(2, 1, 1), "realtime"), (at(1, 31, 23), "realtime"), (at(1, 31, 21), "realtime"), (at(2, 1, 0), "realtime")];
+
+        let res = merge_by_timestamp(archived, realtime, Some(at(2, 1, 0)), timestamp, at(1, 31, 0), at(2, 1, 12));
+
+        // 21:00 is inside the archive's period, so it stays out even though quality control removed it from the archive
+        assert_eq!(res, vec![(at(1, 31, 22), "qc"), (at(1, 31, 23), "qc"), (at(2, 1, 0), "realtime"), (at(2, 1, 1), "realtime")]);
+    }
+
+    #[test]
+    fn uses_every_realtime_record_without_archives() {
+        let realtime = vec![(at(3, 2, 0), "realtime"), (at(3, 1, 0), "realtime"), (at(3, 1, 0), "duplicate"), (at(3, 4, 0), "realtime")];
+
+        let res = merge_by_timestamp(Vec::new(), realtime, None, timestamp, at(3, 1, 0), at(3, 3, 0));
+
+        assert_eq!(res, vec![(at(3, 1, 0), "realtime"), (at(3, 2, 0), "realtime")]);
+    }
+
+    #[test]
+    fn rejects_ranges_that_are_too_long_or_reversed() {
+        assert!(check_range(at(1, 1, 0), at(12, 31, 0)).is_ok());
+        assert!(matches!(check_range(NaiveDate::from_ymd_opt(1990, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap(), at(1, 1, 0)), Err(NdbcError::InvalidQuery { parameter, value }) if parameter == "end" && value.contains("days after start, at most 366")));
+        assert!(matches!(check_range(at(2, 1, 0), at(1, 1, 0)), Err(NdbcError::InvalidQuery { parameter, .. }) if parameter == "start"));
+    }
+}
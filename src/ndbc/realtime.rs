@@ -1,175 +1,286 @@
-use super::ndbc_schema::{check_null_string, ActiveStationsResponse, Station, StationContinuousWindsData, StationDataType, StationRealtimeFile, StationSpectralWaveSummary, StationStdMetData};
+use super::client::{check_station, Fetch, NdbcClient};
+use super::error::NdbcError;
+use super::ndbc_schema::{ActiveStationsResponse, Station, StationContinuousWindsData, StationDartData, StationDataType, StationDriftData, StationLatestObservation, StationOceanCurrentData, StationOceanographicData, StationRainSeries, StationRealtimeFile, StationSolarRadiationData, StationSpectralData, StationSpectralWaveSummary, StationStdMetData, StationSupplementalData, StationTableEntry};
+use super::parser::{parse_adcp, parse_cwind, parse_dart, parse_drift, parse_latest_obs, parse_ocean, parse_rain, parse_spec, parse_spectral, parse_srad, parse_station_table, parse_stdmet, parse_supl};
 use chrono::NaiveDateTime;
 use log::debug;
 use regex::Regex;
 use serde_xml_rs::from_str;
 
-pub async fn get_active_stations() -> Result<Vec<Station>, Box<dyn std::error::Error>> {
-    // This function returns a list of all active stations.
-    // just because a station is active does not mean it has stdmet data.
-    debug!("get_active_stations");
-    let url: &str = "https://www.ndbc.noaa.gov/activestations.xml";
-    debug!("url {}", &url);
+impl NdbcClient {
+    /// Returns every station NDBC currently lists as active.
+    pub async fn get_active_stations(&self) -> Result<Vec<Station>, NdbcError> {
+        // This function returns a list of all active stations.
+        // just because a station is active does not mean it has stdmet data.
+        debug!("get_active_stations");
+        let url: String = self.url("activestations.xml");
+        debug!("url {}", &url);
+
+        let document_url: String = url.clone();
+        let res: Vec<Station> = self.get_parsed(&url, Fetch::Index, move |body| from_str::<ActiveStationsResponse>(body).map(|r| r.stations).map_err(|e| NdbcError::MalformedDocument { url: document_url, reason: e.to_string() })).await?;
+
+        Ok(res)
+    }
+
+    /// Returns the hull, payload, time zone, forecast region and notes NDBC lists for every station.
+    pub async fn get_station_table(&self) -> Result<Vec<StationTableEntry>, NdbcError> {
+        // This function returns the rows of data/stations/station_table.txt, which also covers stations missing from activestations.xml.
+        debug!("get_station_table");
+
+        let url: String = self.url("data/stations/station_table.txt");
+        debug!("url {}", &url);
+
+        let res: Vec<StationTableEntry> = self.get_parsed(&url, Fetch::Index, parse_station_table).await?;
+
+        Ok(res)
+    }
+
+    /// Returns the realtime (last 45 days) files NDBC offers for a data type.
+    pub async fn get_realtime_files(&self, data_type: StationDataType) -> Result<Vec<StationRealtimeFile>, NdbcError> {
+        // This function returns a list of all downloadable realtime files for a specified data_type (eg. stdmet, cwind, swden)
+        debug!("get_realtime_files");
+
+        let url: String = self.url("data/realtime2/");
+        debug!("url {}", &url);
+
+        // the listing is parsed once for every data_type and filtered afterwards, so concurrent lookups can share it
+        let document_url: String = url.clone();
+        let files: Vec<StationRealtimeFile> = self
+            .get_parsed(&url, Fetch::Index, move |body| {
+                let re = Regex::new(r###"<tr><td valign="top"><img src="/icons/text.gif" alt="\[TXT\]"></td><td><a href="(.{5,50})\.(.{2,50})">(.{5,50})</a></td><td align="right">(.{5,50})</td><td align="right">(.{1,50})</td><td>(.{1,50})</td></tr>"###).unwrap();
+
+                re.captures_iter(body)
+                    .map(|c| {
+                        let (_, [s, t, _, ts, _, _]) = c.extract();
+                        Ok(StationRealtimeFile {
+                            filename: s.to_string() + "." + t,
+                            station: s.to_string().to_uppercase(),
+                            data_type: match t {
+                                "txt" => StationDataType::StandardMeteorological,
+                                "cwind" => StationDataType::ContinuousWinds,
+                                "spec" => StationDataType::SpectralWaveSummary,
+                                "adcp" => StationDataType::OceanCurrent,
+                                "ocean" => StationDataType::Oceanographic,
+                                "supl" => StationDataType::Supplemental,
+                                "rain" => StationDataType::Rain,
+                                "srad" => StationDataType::SolarRadiation,
+                                "dart" => StationDataType::Dart,
+                                "data_spec" => StationDataType::SpectralWaveDensity,
+                                "swdir" => StationDataType::SpectralWaveA1Density,
+                                "swdir2" => StationDataType::SpectralWaveA2Density,
+                                "swr1" => StationDataType::SpectralWaveR1Density,
+                                "swr2" => StationDataType::SpectralWaveR2Density,
+                                _ => StationDataType::Unsupported,
+                            },
+                            timestamp: NaiveDateTime::parse_from_str(ts.trim(), "%Y-%m-%d %H:%M").map_err(|e| NdbcError::MalformedDocument { url: document_url.clone(), reason: e.to_string() })?,
+                        })
+                    })
+                    .collect()
+            })
+            .await?;
 
-    let body = reqwest::get(url).await?.text().await?;
+        let res: Vec<StationRealtimeFile> = files.into_iter().filter(|f: &StationRealtimeFile| f.data_type == data_type).collect();
 
-    let res = from_str::<ActiveStationsResponse>(body.as_str()).unwrap();
+        Ok(res)
+    }
 
-    Ok(res.stations)
-}
+    /// Returns the newest standard meteorological observation and position of every station that reported recently.
+    pub async fn get_latest_observations(&self) -> Result<Vec<StationLatestObservation>, NdbcError> {
+        // This function returns data/latest_obs/latest_obs.txt, one call instead of a realtime file per station.
+        debug!("get_latest_observations");
 
-pub async fn get_realtime_files(data_type: StationDataType) -> Result<Vec<StationRealtimeFile>, Box<dyn std::error::Error>> {
-    // This function returns a list of all downloadable realtime files for a specified data_type (eg. stdmet, cwind, swden)
-    debug!("get_realtime_files");
-
-    let url: String = "".to_string() + "https://www.ndbc.noaa.gov/data/realtime2/";
-    let re = Regex::new(r###"<tr><td valign="top"><img src="/icons/text.gif" alt="\[TXT\]"></td><td><a href="(.{5,50})\.(.{2,50})">(.{5,50})</a></td><td align="right">(.{5,50})</td><td align="right">(.{1,50})</td><td>(.{1,50})</td></tr>"###).unwrap();
-    debug!("url {}", &url);
-
-    let body = reqwest::get(url).await?.text().await?;
-
-    let res = re
-        .captures_iter(&body)
-        .map(|c| c.extract())
-        .map(|(_, [s, t, _, ts, _, _])| StationRealtimeFile {
-            filename: s.to_string() + "." + t,
-            station: s.to_string().to_uppercase(),
-            data_type: match t {
-                "txt" => StationDataType::StandardMeteorological,
-                "cwind" => StationDataType::ContinuousWinds,
-                "spec" => StationDataType::SpectralWaveSummary,
-                _ => StationDataType::Unsupported,
-            },
-            timestamp: NaiveDateTime::parse_from_str(&ts.to_string().trim(), "%Y-%m-%d %H:%M").unwrap(),
-        })
-        .filter(|c| c.data_type == data_type)
-        .collect();
-
-    Ok(res)
-}
+        let url: String = self.url("data/latest_obs/latest_obs.txt");
+        debug!("url {}", &url);
 
-pub async fn get_station_realtime_stdmet_data(station: &str) -> Result<Vec<StationStdMetData>, Box<dyn std::error::Error>> {
-    // This function returns the raw stdmet sensor data for a given station over the last 45 days.
-    // This only collects data for stationary buoys, there is a separate function to grab drifting buoy stdmet sensor data.
-    debug!("get_station_realtime_stdmet_data");
-
-    let url: String = "".to_string() + "https://www.ndbc.noaa.gov/data/realtime2/" + station.to_uppercase().as_str() + ".txt";
-    let re = Regex::new(&(r"([0-9M\+\.-]+)[\s\n]+".repeat(19))).unwrap();
-    debug!("url {}", &url);
-
-    let body = reqwest::get(url).await?.text().await?;
-
-    let res = re
-        .captures_iter(&body)
-        .map(|c| c.extract())
-        .map(|(_, [year, month, day, hour, minute, wdir, wspd, gst, wvht, dpd, apd, mwd, pres, atmp, wtmp, dewp, vis, ptdy, tide])| StationStdMetData {
-            station: station.to_string().to_uppercase(),
-            timestamp: NaiveDateTime::parse_from_str(("".to_string() + year + "-" + month + "-" + day + " " + hour + ":" + minute).as_str(), "%Y-%m-%d %H:%M").unwrap(),
-            wdir: if !check_null_string(wdir) { Some(wdir.parse().unwrap()) } else { None },
-            wspd: if !check_null_string(wspd) { Some(wspd.parse().unwrap()) } else { None },
-            gst: if !check_null_string(gst) { Some(gst.parse().unwrap()) } else { None },
-            wvht: if !check_null_string(wvht) { Some(wvht.parse().unwrap()) } else { None },
-            dpd: if !check_null_string(dpd) { Some(dpd.parse().unwrap()) } else { None },
-            apd: if !check_null_string(apd) { Some(apd.parse().unwrap()) } else { None },
-            mwd: if !check_null_string(mwd) { Some(mwd.parse().unwrap()) } else { None },
-            pres: if !check_null_string(pres) { Some(pres.parse().unwrap()) } else { None },
-            atmp: if !check_null_string(atmp) { Some(atmp.parse().unwrap()) } else { None },
-            wtmp: if !check_null_string(wtmp) { Some(wtmp.parse().unwrap()) } else { None },
-            dewp: if !check_null_string(dewp) { Some(dewp.parse().unwrap()) } else { None },
-            vis: if !check_null_string(vis) { Some(vis.parse().unwrap()) } else { None },
-            ptdy: if !check_null_string(ptdy) { Some(ptdy.parse().unwrap()) } else { None },
-            tide: if !check_null_string(tide) { Some(tide.parse().unwrap()) } else { None },
-        })
-        .collect();
-
-    Ok(res)
-}
+        let res: Vec<StationLatestObservation> = self.get_parsed(&url, Fetch::Text, parse_latest_obs).await?;
 
-pub async fn get_station_realtime_stdmetdrift_data(station: &str) -> Result<Vec<StationStdMetData>, Box<dyn std::error::Error>> {
-    // This function returns the raw stdmet sensor data for a given station over the last 45 days.
-    // This only collects data for stationary buoys, there is a separate function to grab drifting buoy stdmet sensor data.
-    debug!("get_station_realtime_stdmetdrift_data");
-
-    let url: String = "".to_string() + "https://www.ndbc.noaa.gov/data/realtime2/" + station.to_uppercase().as_str() + ".drift";
-    debug!("{}", &url);
-
-    let re = Regex::new(&(r"([0-9M\+\.-]+)[\s\n]+".repeat(16))).unwrap();
-
-    let body = reqwest::get(url).await?.text().await?;
-
-    let res = re
-        .captures_iter(&body)
-        .map(|c| c.extract())
-        .map(|(_, [year, month, day, hourminute, _lat, _lon, wdir, gst, wspd, pres, ptdy, atmp, wtmp, dewp, wvht, dpd])| StationStdMetData {
-            // re-use stdmet struct even though some data is dropped
-            station: station.to_string().to_uppercase(),
-            timestamp: NaiveDateTime::parse_from_str(("".to_string() + year + "-" + month + "-" + day + " " + hourminute).as_str(), "%Y-%m-%d %H%M").unwrap(),
-            wdir: if !check_null_string(wdir) { Some(wdir.parse().unwrap()) } else { None },
-            wspd: if !check_null_string(wspd) { Some(wspd.parse().unwrap()) } else { None },
-            gst: if !check_null_string(gst) { Some(gst.parse().unwrap()) } else { None },
-            wvht: if !check_null_string(wvht) { Some(wvht.parse().unwrap()) } else { None },
-            dpd: if !check_null_string(dpd) { Some(dpd.parse().unwrap()) } else { None },
-            apd: None,
-            mwd: None,
-            pres: if !check_null_string(pres) { Some(pres.parse().unwrap()) } else { None },
-            atmp: if !check_null_string(atmp) { Some(atmp.parse().unwrap()) } else { None },
-            wtmp: if !check_null_string(wtmp) { Some(wtmp.parse().unwrap()) } else { None },
-            dewp: if !check_null_string(dewp) { Some(dewp.parse().unwrap()) } else { None },
-            vis: None,
-            ptdy: if !check_null_string(ptdy) { Some(ptdy.parse().unwrap()) } else { None },
-            tide: None,
-        })
-        .collect();
-
-    Ok(res)
-}
+        Ok(res)
+    }
 
-pub async fn get_station_realtime_cwind_data(station: &str) -> Result<Vec<StationContinuousWindsData>, Box<dyn std::error::Error>> {
-    // This function returns the raw stdmet sensor data for a given station over the last 45 days.
-    // This only collects data for stationary buoys, there is a separate function to grab drifting buoy stdmet sensor data.
-    debug!("get_station_realtime_cwind_data");
+    /// Returns the realtime (last 45 days) standard meteorological data for a station.
+    pub async fn get_station_realtime_stdmet_data(&self, station: &str) -> Result<Vec<StationStdMetData>, NdbcError> {
+        // This function returns the raw stdmet sensor data for a given station over the last 45 days.
+        // This only collects data for stationary buoys, there is a separate function to grab drifting buoy stdmet sensor data.
+        debug!("get_station_realtime_stdmet_data");
 
-    let url: String = "".to_string() + "https://www.ndbc.noaa.gov/data/realtime2/" + station.to_uppercase().as_str() + ".cwind";
-    debug!("{}", &url);
+        check_station(station)?;
 
-    let re = Regex::new(&(r"([0-9M\+\.-]+)[\s\n]+".repeat(10))).unwrap();
+        let url: String = self.url("data/realtime2/") + station.to_uppercase().as_str() + ".txt";
+        debug!("url {}", &url);
 
-    let body = reqwest::get(url).await?.text().await?;
+        let station: String = station.to_uppercase();
+        let res = self.get_parsed(&url, Fetch::Text, move |body| parse_stdmet(&station, body)).await?;
 
-    let res = re.captures_iter(&body).map(|c| c.extract()).map(|(_, [year, month, day, hour, minute, wdir, wspd, gdr, gst, _gtime])| StationContinuousWindsData { station: station.to_string().to_uppercase(), timestamp: NaiveDateTime::parse_from_str(("".to_string() + year + "-" + month + "-" + day + " " + hour + ":" + minute).as_str(), "%Y-%m-%d %H:%M").unwrap(), wdir: if !check_null_string(wdir) { Some(wdir.parse().unwrap()) } else { None }, wspd: if !check_null_string(wspd) { Some(wspd.parse().unwrap()) } else { None }, gdr: if !check_null_string(gdr) { Some(gdr.parse().unwrap()) } else { None }, gst: if !check_null_string(gst) { Some(gst.parse().unwrap()) } else { None } }).collect();
+        Ok(res)
+    }
 
-    Ok(res)
-}
+    /// Returns the realtime (last 45 days) standard meteorological data and positions for a drifting buoy.
+    pub async fn get_station_realtime_stdmetdrift_data(&self, station: &str) -> Result<Vec<StationDriftData>, NdbcError> {
+        // This function returns the raw stdmet sensor data for a given drifting buoy over the last 45 days, with the position of every observation.
+        debug!("get_station_realtime_stdmetdrift_data");
+
+        check_station(station)?;
+
+        let url: String = self.url("data/realtime2/") + station.to_uppercase().as_str() + ".drift";
+        debug!("{}", &url);
+
+        let station: String = station.to_uppercase();
+        let res = self.get_parsed(&url, Fetch::Text, move |body| parse_drift(&station, body)).await?;
+
+        Ok(res)
+    }
+
+    /// Returns the realtime (last 45 days) continuous winds data for a station.
+    pub async fn get_station_realtime_cwind_data(&self, station: &str) -> Result<Vec<StationContinuousWindsData>, NdbcError> {
+        // This function returns the raw stdmet sensor data for a given station over the last 45 days.
+        // This only collects data for stationary buoys, there is a separate function to grab drifting buoy stdmet sensor data.
+        debug!("get_station_realtime_cwind_data");
+
+        check_station(station)?;
+
+        let url: String = self.url("data/realtime2/") + station.to_uppercase().as_str() + ".cwind";
+        debug!("{}", &url);
+
+        let station: String = station.to_uppercase();
+        let res = self.get_parsed(&url, Fetch::Text, move |body| parse_cwind(&station, body)).await?;
+
+        Ok(res)
+    }
+
+    /// Returns the realtime (last 45 days) spectral wave summary for a station.
+    pub async fn get_station_realtime_spec_data(&self, station: &str) -> Result<Vec<StationSpectralWaveSummary>, NdbcError> {
+        // This function returns the spectral wave summary sensor data for a given station over the last 45 days.
+        debug!("get_station_realtime_spec_data");
+
+        check_station(station)?;
+
+        let url: String = self.url("data/realtime2/") + station.to_uppercase().as_str() + ".spec";
+        debug!("{:?}", &url);
+
+        let station: String = station.to_uppercase();
+        let res = self.get_parsed(&url, Fetch::Text, move |body| parse_spec(&station, body)).await?;
+
+        Ok(res)
+    }
+
+    /// Returns the realtime (last 45 days) ocean current profiles for a station.
+    pub async fn get_station_realtime_adcp_data(&self, station: &str) -> Result<Vec<StationOceanCurrentData>, NdbcError> {
+        // This function returns the ocean current speed and direction at each depth bin for a given station over the last 45 days.
+        debug!("get_station_realtime_adcp_data");
+
+        check_station(station)?;
+
+        let url: String = self.url("data/realtime2/") + station.to_uppercase().as_str() + ".adcp";
+        debug!("{}", &url);
+
+        let station: String = station.to_uppercase();
+        let res = self.get_parsed(&url, Fetch::Text, move |body| parse_adcp(&station, body)).await?;
+
+        Ok(res)
+    }
+
+    /// Returns the realtime (last 45 days) oceanographic and water quality data for a station.
+    pub async fn get_station_realtime_ocean_data(&self, station: &str) -> Result<Vec<StationOceanographicData>, NdbcError> {
+        // This function returns the ocean temperature, salinity, oxygen, chlorophyll, turbidity, pH and Eh for a given station over the last 45 days.
+        debug!("get_station_realtime_ocean_data");
+
+        check_station(station)?;
+
+        let url: String = self.url("data/realtime2/") + station.to_uppercase().as_str() + ".ocean";
+        debug!("{}", &url);
+
+        let station: String = station.to_uppercase();
+        let res = self.get_parsed(&url, Fetch::Text, move |body| parse_ocean(&station, body)).await?;
+
+        Ok(res)
+    }
+
+    /// Returns the realtime (last 45 days) lowest 1-minute pressure and highest 1-minute wind of each hour for a station.
+    pub async fn get_station_realtime_supl_data(&self, station: &str) -> Result<Vec<StationSupplementalData>, NdbcError> {
+        // This function returns the supplemental measurements for a given station over the last 45 days, these are the storm peaks the hourly stdmet values average out.
+        debug!("get_station_realtime_supl_data");
+
+        check_station(station)?;
+
+        let url: String = self.url("data/realtime2/") + station.to_uppercase().as_str() + ".supl";
+        debug!("{}", &url);
+
+        let station: String = station.to_uppercase();
+        let res = self.get_parsed(&url, Fetch::Text, move |body| parse_supl(&station, body)).await?;
+
+        Ok(res)
+    }
+
+    /// Returns the realtime (last 45 days) hourly precipitation for a station.
+    pub async fn get_station_realtime_rain_data(&self, station: &str) -> Result<StationRainSeries, NdbcError> {
+        // This function returns the hourly rain accumulation for a given station over the last 45 days, along with the units reported in the file.
+        debug!("get_station_realtime_rain_data");
+
+        check_station(station)?;
+
+        let url: String = self.url("data/realtime2/") + station.to_uppercase().as_str() + ".rain";
+        debug!("{}", &url);
+
+        let station: String = station.to_uppercase();
+        let res = self.get_parsed(&url, Fetch::Text, move |body| parse_rain(&station, body)).await?;
+
+        Ok(res)
+    }
+
+    /// Returns the realtime (last 45 days) solar radiation data for a station.
+    pub async fn get_station_realtime_srad_data(&self, station: &str) -> Result<Vec<StationSolarRadiationData>, NdbcError> {
+        // This function returns the shortwave and longwave radiation for a given station over the last 45 days.
+        debug!("get_station_realtime_srad_data");
+
+        check_station(station)?;
+
+        let url: String = self.url("data/realtime2/") + station.to_uppercase().as_str() + ".srad";
+        debug!("{}", &url);
+
+        let station: String = station.to_uppercase();
+        let res = self.get_parsed(&url, Fetch::Text, move |body| parse_srad(&station, body)).await?;
+
+        Ok(res)
+    }
+
+    /// Returns the realtime (last 45 days) DART water column height for a station.
+    pub async fn get_station_realtime_dart_data(&self, station: &str) -> Result<Vec<StationDartData>, NdbcError> {
+        // This function returns the tsunameter water column height for a given station over the last 45 days, including any event mode reports.
+        debug!("get_station_realtime_dart_data");
+
+        check_station(station)?;
+
+        let url: String = self.url("data/realtime2/") + station.to_uppercase().as_str() + ".dart";
+        debug!("{}", &url);
+
+        let station: String = station.to_uppercase();
+        let res = self.get_parsed(&url, Fetch::Text, move |body| parse_dart(&station, body)).await?;
+
+        Ok(res)
+    }
+
+    /// Returns the realtime (last 45 days) frequency binned spectra of a spectral data type for a station.
+    pub async fn get_station_realtime_spectral_data(&self, station: &str, data_type: StationDataType) -> Result<Vec<StationSpectralData>, NdbcError> {
+        // This function returns the spectral wave density (data_spec) or one of its directional components (swdir, swdir2, swr1, swr2) for a given station over the last 45 days.
+        debug!("get_station_realtime_spectral_data");
+
+        let extension: &str = match data_type {
+            StationDataType::SpectralWaveDensity => "data_spec",
+            StationDataType::SpectralWaveA1Density => "swdir",
+            StationDataType::SpectralWaveA2Density => "swdir2",
+            StationDataType::SpectralWaveR1Density => "swr1",
+            StationDataType::SpectralWaveR2Density => "swr2",
+            _ => return Err(NdbcError::UnsupportedDataType { data_type: data_type.as_str().to_string() }),
+        };
+
+        check_station(station)?;
+
+        let url: String = self.url("data/realtime2/") + station.to_uppercase().as_str() + "." + extension;
+        debug!("{}", &url);
+
+        let station: String = station.to_uppercase();
+        let res = self.get_parsed(&url, Fetch::Text, move |body| parse_spectral(&station, data_type, body)).await?;
 
-pub async fn get_station_realtime_spec_data(station: &str) -> Result<Vec<StationSpectralWaveSummary>, Box<dyn std::error::Error>> {
-    // This function returns the spectral wave summary sensor data for a given station over the last 45 days.
-    debug!("get_station_realtime_spec_data");
-
-    let url: String = "".to_string() + "https://www.ndbc.noaa.gov/data/realtime2/" + station.to_uppercase().as_str() + ".spec";
-    debug!("{:?}", &url);
-
-    let re = Regex::new(&(r"(?m)^".to_string() + &r"([A-Z0-9\+\.\-_]+)[\s\n]+".repeat(15))).unwrap();
-
-    let body = reqwest::get(url).await?.text().await?;
-
-    let res = re
-        .captures_iter(&body)
-        .map(|c| c.extract())
-        .map(|(_, [year, month, day, hour, minute, wvht, swh, swp, wwh, wwp, swd, wwd, steep, apd, mwd])| StationSpectralWaveSummary {
-            station: station.to_string().to_uppercase(),
-            timestamp: NaiveDateTime::parse_from_str(("".to_string() + year + "-" + month + "-" + day + " " + hour + ":" + minute).as_str(), "%Y-%m-%d %H:%M").unwrap(),
-            wvht: if !check_null_string(wvht) { Some(wvht.parse().unwrap()) } else { None },
-            swh: if !check_null_string(swh) { Some(swh.parse().unwrap()) } else { None },
-            swp: if !check_null_string(swp) { Some(swp.parse().unwrap()) } else { None },
-            wwh: if !check_null_string(wwh) { Some(wwh.parse().unwrap()) } else { None },
-            wwp: if !check_null_string(wwp) { Some(wwp.parse().unwrap()) } else { None },
-            swd: if !check_null_string(swd) { Some(swd.parse().unwrap()) } else { None },
-            wwd: if !check_null_string(wwd) { Some(wwd.parse().unwrap()) } else { None },
-            steep: if !check_null_string(steep) { Some(steep.parse().unwrap()) } else { None },
-            apd: if !check_null_string(apd) { Some(apd.parse().unwrap()) } else { None },
-            mwd: if !check_null_string(mwd) { Some(mwd.parse().unwrap()) } else { None },
-        })
-        .collect();
-
-    Ok(res)
+        Ok(res)
+    }
 }
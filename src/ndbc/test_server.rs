@@ -0,0 +1,144 @@
+//! A minimal HTTP server standing in for NDBC in tests, it serves canned responses and records every request.
+
+use super::client::{NdbcClientConfig, NdbcRetryConfig};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
+
+#[derive(Debug, Clone)]
+pub struct TestResponse {
+    pub status: u16,
+    pub body: Vec<u8>,
+    pub etag: Option<String>,
+    pub delay: Duration,
+}
+
+impl TestResponse {
+    pub fn ok(body: impl Into<Vec<u8>>) -> Self {
+        TestResponse { status: 200, body: body.into(), etag: None, delay: Duration::ZERO }
+    }
+
+    pub fn status(status: u16) -> Self {
+        TestResponse { status, body: Vec::new(), etag: None, delay: Duration::ZERO }
+    }
+
+    pub fn with_etag(mut self, etag: &str) -> Self {
+        self.etag = Some(etag.to_string());
+        self
+    }
+
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TestRequest {
+    pub path: String,
+    // header names are lower-case
+    pub headers: Vec<(String, String)>,
+}
+
+impl TestRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+}
+
+#[derive(Debug, Default)]
+struct TestServerState {
+    routes: HashMap<String, TestResponse>,
+    requests: Vec<TestRequest>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TestServer {
+    pub base_url: String,
+    state: Arc<Mutex<TestServerState>>,
+}
+
+impl TestServer {
+    pub fn start() -> Self {
+        // every connection is answered on its own thread and closed afterwards, so concurrent and delayed responses do not block each other
+        let listener: TcpListener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url: String = format!("http://{}", listener.local_addr().unwrap());
+        let state: Arc<Mutex<TestServerState>> = Arc::default();
+
+        let server_state: Arc<Mutex<TestServerState>> = state.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let state: Arc<Mutex<TestServerState>> = server_state.clone();
+                std::thread::spawn(move || respond(stream, &state));
+            }
+        });
+
+        TestServer { base_url, state }
+    }
+
+    /// Serves the response for a path (including any query string), replacing whatever was served before.
+    pub fn route(&self, path: &str, response: TestResponse) {
+        self.state.lock().unwrap().routes.insert(path.to_string(), response);
+    }
+
+    pub fn requests(&self, path: &str) -> Vec<TestRequest> {
+        self.state.lock().unwrap().requests.iter().filter(|r| r.path == path).cloned().collect()
+    }
+
+    /// A client configuration pointed at this server, retrying without any noticeable backoff.
+    pub fn config(&self) -> NdbcClientConfig {
+        NdbcClientConfig { base_url: self.base_url.clone(), retry: NdbcRetryConfig { max_retries: 2, initial_backoff: Duration::from_millis(1), max_backoff: Duration::from_millis(5) }, ..Default::default() }
+    }
+}
+
+/// Compresses a body the way NDBC's archives are (eg. 46014h2019.txt.gz).
+pub async fn gzip(body: &str) -> Vec<u8> {
+    let mut res: Vec<u8> = Vec::new();
+    async_compression::tokio::bufread::GzipEncoder::new(body.as_bytes()).read_to_end(&mut res).await.unwrap();
+
+    res
+}
+
+fn respond(mut stream: TcpStream, state: &Mutex<TestServerState>) {
+    let mut head: Vec<u8> = Vec::new();
+    let mut buf: [u8; 1024] = [0; 1024];
+
+    while !head.ends_with(b"\r\n\r\n") {
+        match stream.read(&mut buf) {
+            Ok(0) | Err(_) => return,
+            Ok(n) => head.extend_from_slice(&buf[..n]),
+        }
+    }
+
+    let head: String = String::from_utf8_lossy(&head).into_owned();
+    let mut lines = head.lines();
+    let path: String = lines.next().and_then(|l| l.split_whitespace().nth(1)).unwrap_or_default().to_string();
+    let headers: Vec<(String, String)> = lines.filter_map(|l| l.split_once(':')).map(|(n, v)| (n.trim().to_lowercase(), v.trim().to_string())).collect();
+    let request: TestRequest = TestRequest { path: path.clone(), headers };
+
+    let response: TestResponse = {
+        let mut state = state.lock().unwrap();
+        state.requests.push(request.clone());
+        state.routes.get(&path).cloned().unwrap_or_else(|| TestResponse::status(404))
+    };
+
+    std::thread::sleep(response.delay);
+
+    // a matching If-None-Match is answered with 304 like NDBC's web server does
+    let (status, body): (u16, &[u8]) = match (&response.etag, request.header("if-none-match")) {
+        (Some(etag), Some(given)) if etag == given => (304, &[]),
+        _ => (response.status, &response.body),
+    };
+
+    let mut out: Vec<u8> = format!("HTTP/1.1 {status} Test\r\nContent-Length: {}\r\nConnection: close\r\n", body.len()).into_bytes();
+    if let Some(etag) = &response.etag {
+        out.extend_from_slice(format!("ETag: {etag}\r\n").as_bytes());
+    }
+    out.extend_from_slice(b"\r\n");
+    out.extend_from_slice(body);
+
+    let _ = stream.write_all(&out);
+}
@@ -0,0 +1,150 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+/// How often and how patiently failed upstream requests are retried.
+#[derive(Debug, Clone)]
+pub struct NdbcRetryConfig {
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for NdbcRetryConfig {
+    fn default() -> Self {
+        NdbcRetryConfig { max_retries: 3, initial_backoff: Duration::from_millis(500), max_backoff: Duration::from_secs(10) }
+    }
+}
+
+impl NdbcRetryConfig {
+    pub(crate) fn backoff(&self, attempt: u32) -> Duration {
+        // exponential backoff with full jitter, so clients that failed together do not retry together
+        self.backoff_ceiling(attempt).mul_f64(fastrand::f64())
+    }
+
+    fn backoff_ceiling(&self, attempt: u32) -> Duration {
+        self.initial_backoff.saturating_mul(2u32.saturating_pow(attempt)).min(self.max_backoff)
+    }
+}
+
+/// How fast and how many requests at once are sent to NDBC.
+#[derive(Debug, Clone)]
+pub struct NdbcRateLimitConfig {
+    pub requests_per_second: f64,
+    pub burst: u32,
+    pub max_concurrent: usize,
+}
+
+impl Default for NdbcRateLimitConfig {
+    fn default() -> Self {
+        NdbcRateLimitConfig { requests_per_second: 10.0, burst: 20, max_concurrent: 8 }
+    }
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+#[derive(Debug)]
+pub struct Throttle {
+    config: NdbcRateLimitConfig,
+    bucket: Mutex<TokenBucket>,
+    permits: Semaphore,
+}
+
+impl Throttle {
+    pub fn new(config: NdbcRateLimitConfig) -> Self {
+        let bucket: TokenBucket = TokenBucket { tokens: config.burst.max(1) as f64, updated: Instant::now() };
+        let permits: Semaphore = Semaphore::new(config.max_concurrent.max(1));
+
+        Throttle { config, bucket: Mutex::new(bucket), permits }
+    }
+
+    pub async fn acquire(&self) -> SemaphorePermit<'_> {
+        // This function waits for a free connection slot and then for a token, the slot is held until the permit is dropped.
+        // A rate of 0 or less (or one that is not finite) disables the token bucket and only the concurrency cap applies.
+        let permit: SemaphorePermit<'_> = self.permits.acquire().await.expect("the throttle semaphore is never closed");
+
+        if !(self.config.requests_per_second.is_finite() && self.config.requests_per_second > 0.0) {
+            return permit;
+        }
+
+        loop {
+            let wait: Option<Duration> = {
+                let mut bucket = self.bucket.lock().unwrap_or_else(|e| e.into_inner());
+                let now: Instant = Instant::now();
+
+                bucket.tokens = (bucket.tokens + now.duration_since(bucket.updated).as_secs_f64() * self.config.requests_per_second).min(self.config.burst.max(1) as f64);
+                bucket.updated = now;
+
+                if bucket.tokens >= 1.0 {
+                    bucket.tokens -= 1.0;
+                    None
+                } else {
+                    Some(Duration::from_secs_f64((1.0 - bucket.tokens) / self.config.requests_per_second))
+                }
+            };
+
+            match wait {
+                Some(wait) => tokio::time::sleep(wait).await,
+                None => return permit,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::time::timeout;
+
+    fn throttle(requests_per_second: f64, max_concurrent: usize) -> Throttle {
+        Throttle::new(NdbcRateLimitConfig { requests_per_second, burst: 1, max_concurrent })
+    }
+
+    #[test]
+    fn grows_the_backoff_up_to_the_cap() {
+        let config = NdbcRetryConfig { max_retries: 3, initial_backoff: Duration::from_millis(100), max_backoff: Duration::from_secs(1) };
+
+        let ceilings: Vec<u128> = [0, 1, 2, 3, 4, 40].map(|attempt| config.backoff_ceiling(attempt).as_millis()).to_vec();
+        assert_eq!(ceilings, vec![100, 200, 400, 800, 1000, 1000]);
+
+        for attempt in 0..8 {
+            assert!(config.backoff(attempt) <= config.backoff_ceiling(attempt));
+        }
+    }
+
+    #[tokio::test]
+    async fn waits_for_a_token_once_the_burst_is_spent() {
+        let throttle = throttle(1.0, 8);
+
+        drop(throttle.acquire().await);
+
+        assert!(timeout(Duration::from_millis(100), throttle.acquire()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn disables_the_token_bucket_for_rates_that_are_not_positive_and_finite() {
+        for rate in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            let throttle = throttle(rate, 8);
+
+            for _ in 0..20 {
+                assert!(timeout(Duration::from_millis(100), throttle.acquire()).await.is_ok(), "{rate}");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn limits_the_requests_in_flight() {
+        let throttle = throttle(0.0, 2);
+
+        let first = throttle.acquire().await;
+        let _second = throttle.acquire().await;
+        assert!(timeout(Duration::from_millis(50), throttle.acquire()).await.is_err());
+
+        drop(first);
+        assert!(timeout(Duration::from_millis(50), throttle.acquire()).await.is_ok());
+    }
+}
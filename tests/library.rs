@@ -0,0 +1,25 @@
+//! The library is usable on its own, without the server feature.
+
+use rust_service_ndbc_noaa::ndbc::client::{NdbcCacheConfig, NdbcClient, NdbcClientConfig, NdbcRateLimitConfig, NdbcRetryConfig};
+use rust_service_ndbc_noaa::ndbc::ndbc_schema::{StationDataType, StationHistoricFile};
+use rust_service_ndbc_noaa::ndbc::parser::parse_spec;
+use std::time::Duration;
+
+#[test]
+fn parses_a_downloaded_file() {
+    let body = "#YY  MM DD hh mm WVHT  SwH  SwP  WWH  WWP SwD WWD  STEEPNESS  APD MWD\n#yr  mo dy hr mn    m    m  sec    m  sec  -  degT     -      sec degT\n2024 10 01 00 40  1.6  1.4 11.4  0.6  5.3 WNW  NW    AVERAGE  7.2 295\n";
+    let res = parse_spec("46014", body).unwrap();
+
+    assert_eq!((res[0].wvht, res[0].swd.as_deref(), res[0].mwd), (Some(1.6), Some("WNW"), Some(295.0)));
+}
+
+#[test]
+fn configures_a_client() {
+    let config = NdbcClientConfig { base_url: "https://mirror.example/ndbc/".to_string(), cache: NdbcCacheConfig { max_entries: 16, ..Default::default() }, retry: NdbcRetryConfig { max_retries: 0, ..Default::default() }, rate_limit: NdbcRateLimitConfig { requests_per_second: 2.0, burst: 4, max_concurrent: 2 }, timeout: Duration::from_secs(5), ..Default::default() };
+    let client = NdbcClient::new(config).unwrap();
+    let file = StationHistoricFile::new("46014", StationDataType::StandardMeteorological, "2019");
+
+    assert_eq!(client.base_url(), "https://mirror.example/ndbc");
+    assert_eq!(client.cache_stats().max_entries, 16);
+    assert_eq!(file.path(), "data/historical/stdmet/46014h2019.txt.gz");
+}